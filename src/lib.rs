@@ -4,9 +4,10 @@ pub mod orbit;
 
 pub use orbit::*;
 pub use tree::{Tree, loader::StandardLoader};
-pub use loader::FileLoader;
+pub use loader::{FileLoader, LoadIntoError, ReadSeek};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FileEntryType {
     Directory,
     File
@@ -26,49 +27,76 @@ impl FileEntryType {
 }
 
 /// Determines how `orbits` will react to files which have file conflicts.
-/// - `Strict` will cause `orbits` to panic when a conflict is encountered. The `PathBuf` will be the full path to the conflicting file
-/// - `NoRoot` will cause all of the files in the conflciting root to be removed from the tree if a child is encountered. Note that this is first come first serve **only**.
+/// - `Strict` will cause discovery to return a `DiscoveryError::Conflict` with the full paths to both conflicting files
+/// - `NoRoot` will reject the conflicting root entirely, rolling back every file, directory and collected path it added. Note that this is first come first serve **only**.
 /// - `First` will keep the first file in all file conflicts.
 /// - `Last` will cause all files to overwrite the previous file in all file conflicts
+/// - `Priority` will keep the file from the root with the higher priority (see `LaunchPad::set_root_priority`), regardless of discovery order.
+///   Roots with equal priorities are ordered by their path, with the lesser path winning.
 #[derive(Copy, Clone)]
 pub enum ConflictHandler {
     Strict,
     NoRoot,
     First,
-    Last
+    Last,
+    Priority
 }
 #[cfg(test)]
 mod tests {
     extern crate zip;
-    use std::{fs::File, io::Read};
+    use std::{cell::RefCell, fs::File, io::{Read, Seek, SeekFrom, Write}};
 
-    use super::ConflictHandler;
+    use super::{ConflictHandler, FileEntryType};
     use crate::{loader::FileLoader, tree::loader::StandardLoader};
 
     use super::{tree, orbit};
+
+    fn test_root(name: &str) -> std::path::PathBuf {
+        let root = std::env::temp_dir().join("orbits_tests").join(name);
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn write_test_file<P: AsRef<std::path::Path>>(root: &std::path::Path, local_path: P, data: &[u8]) {
+        let path = root.join(local_path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, data).unwrap();
+    }
+
+    fn users_root(name: &str) -> std::path::PathBuf {
+        let root = test_root(name);
+        write_test_file(&root, "coe_a/Downloads/some_file.txt", b"some data");
+        std::fs::create_dir_all(root.join("coe_a/Documents")).unwrap();
+        root
+    }
+
     #[test]
     fn basic_add_test() {
+        let users = users_root("basic_add_test");
         let mut tree = tree::Tree::new(tree::loader::StandardLoader {});
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Downloads").is_none());
+        assert!(tree.insert_path(&users, "coe_a/Downloads").is_none());
     }
     
     #[test]
     fn multi_add_test() {
+        let users = users_root("multi_add_test");
         let mut tree = tree::Tree::new(tree::loader::StandardLoader {});
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Downloads").is_none());
-        assert!(tree.insert_file("/mnt/c/Users", "coe_a/Downloads/some_file.txt").is_none());
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Documents").is_none());
-        let (root, local) = tree.insert_directory("/mnt/c/Users/something_else", "coe_a/Downloads").unwrap();
+        assert!(tree.insert_path(&users, "coe_a/Downloads").is_none());
+        assert!(tree.insert_file(&users, "coe_a/Downloads/some_file.txt").is_none());
+        assert!(tree.insert_path(&users, "coe_a/Documents").is_none());
+        let (root, local) = tree.insert_directory(users.join("something_else"), "coe_a/Downloads").unwrap();
         assert!(root == std::path::Path::new(""));
         assert!(local == std::path::Path::new("coe_a/Downloads"));
     }
     
     #[test]
     fn remove_test() {
+        let users = users_root("remove_test");
         let mut tree = tree::Tree::new(tree::loader::StandardLoader {});
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Downloads").is_none());
-        assert!(tree.insert_file("/mnt/c/Users", "coe_a/Downloads/some_file.txt").is_none());
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Documents").is_none());
+        assert!(tree.insert_path(&users, "coe_a/Downloads").is_none());
+        assert!(tree.insert_file(&users, "coe_a/Downloads/some_file.txt").is_none());
+        assert!(tree.insert_path(&users, "coe_a/Documents").is_none());
         assert!(tree.remove_path("coe_a/Documents").is_some());
         tree.walk_paths(|node, _| {
             assert!(node.local_path != std::path::Path::new("coe_a/Documents"));
@@ -77,25 +105,27 @@ mod tests {
     
     #[test]
     fn remove_root_test() {
+        let users = users_root("remove_root_test");
         
         let mut tree = tree::Tree::new(tree::loader::StandardLoader {});
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Downloads").is_none());
-        assert!(tree.insert_file("/mnt/c/Users", "coe_a/Downloads/some_file.txt").is_none());
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Documents").is_none());
-        assert!(tree.insert_directory("/mnt/c/Users/test", "coe_a/Documents/test").is_none());
-        assert!(tree.remove_paths_by_root("/mnt/c/Users").len() == 1);
+        assert!(tree.insert_path(&users, "coe_a/Downloads").is_none());
+        assert!(tree.insert_file(&users, "coe_a/Downloads/some_file.txt").is_none());
+        assert!(tree.insert_path(&users, "coe_a/Documents").is_none());
+        assert!(tree.insert_directory(users.join("test"), "coe_a/Documents/test").is_none());
+        assert!(tree.remove_paths_by_root(&users).len() == 1);
         tree.walk_paths(|node, _| {
-            assert!(node.root_path != std::path::Path::new("/mnt/c/Users"));
+            assert!(node.root_path != users);
         });
     }
     
     #[test]
     fn filter_walk_paths_test() {
+        let users = users_root("filter_walk_paths_test");
         let mut tree = tree::Tree::new(tree::loader::StandardLoader {});
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Downloads").is_none());
-        assert!(tree.insert_file("/mnt/c/Users", "coe_a/Downloads/some_file.txt").is_none());
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Documents").is_none());
-        assert!(tree.insert_directory("/mnt/c/Users/test", "coe_a/Documents/test").is_none());
+        assert!(tree.insert_path(&users, "coe_a/Downloads").is_none());
+        assert!(tree.insert_file(&users, "coe_a/Downloads/some_file.txt").is_none());
+        assert!(tree.insert_path(&users, "coe_a/Documents").is_none());
+        assert!(tree.insert_directory(users.join("test"), "coe_a/Documents/test").is_none());
         assert!(tree.filter_walk_paths(|_, entry_type| {
             if entry_type.is_file() {
                 Some(())
@@ -110,11 +140,12 @@ mod tests {
     
     #[test]
     fn purify_test() {
+        let users = users_root("purify_test");
         let mut tree = tree::Tree::new(tree::loader::StandardLoader {});
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Downloads").is_none());
-        assert!(tree.insert_file("/mnt/c/Users", "coe_a/Downloads/some_file.txt").is_none());
-        assert!(tree.insert_path("/mnt/c/Users", "coe_a/Documents").is_none());
-        assert!(tree.insert_directory("/mnt/c/Users/test", "coe_a/Documents/test").is_none());
+        assert!(tree.insert_path(&users, "coe_a/Downloads").is_none());
+        assert!(tree.insert_file(&users, "coe_a/Downloads/some_file.txt").is_none());
+        assert!(tree.insert_path(&users, "coe_a/Documents").is_none());
+        assert!(tree.insert_directory(users.join("test"), "coe_a/Documents/test").is_none());
         tree.purify();
         tree.walk_paths(|node, _| {
             assert!(node.local_path != std::path::Path::new("coe_a/Documents/test"));
@@ -123,18 +154,30 @@ mod tests {
     }
 
     struct ZipLoader {
-        archive: zip::ZipArchive<File>
+        archive: RefCell<zip::ZipArchive<File>>
+    }
+
+    impl ZipLoader {
+        fn new<P: AsRef<std::path::Path>>(path: P) -> Self {
+            Self {
+                archive: RefCell::new(zip::ZipArchive::new(File::open(path).unwrap()).unwrap())
+            }
+        }
     }
 
     impl FileLoader for ZipLoader {
         type ErrorType = zip::result::ZipError;
 
         fn path_exists(&self, _: &std::path::Path, local_path: &std::path::Path) -> bool {
-            unsafe { std::mem::transmute::<*const zip::ZipArchive<File>, &mut zip::ZipArchive<File>>(&self.archive as *const zip::ZipArchive<File>) }.by_name(local_path.to_str().unwrap()).is_ok()
+            self.archive.borrow_mut().by_name(local_path.to_str().unwrap()).is_ok()
+        }
+
+        fn get_file_size(&self, _: &std::path::Path, local_path: &std::path::Path) -> Option<usize> {
+            self.archive.borrow_mut().by_name(local_path.to_str().unwrap()).ok().map(|file| file.size() as usize)
         }
 
         fn get_path_type(&self, _: &std::path::Path, local_path: &std::path::Path) -> Result<crate::FileEntryType, Self::ErrorType> {
-            if unsafe { std::mem::transmute::<*const zip::ZipArchive<File>, &mut zip::ZipArchive<File>>(&self.archive as *const zip::ZipArchive<File>) }.by_name(local_path.to_str().unwrap())?.is_dir() {
+            if self.archive.borrow_mut().by_name(local_path.to_str().unwrap())?.is_dir() {
                 Ok(crate::FileEntryType::Directory)
             } else {
                 Ok(crate::FileEntryType::File)
@@ -142,7 +185,8 @@ mod tests {
         }
 
         fn load_path(&self, _: &std::path::Path, local_path: &std::path::Path) -> Result<Vec<u8>, Self::ErrorType> {
-            let mut file = unsafe { std::mem::transmute::<*const zip::ZipArchive<File>, &mut zip::ZipArchive<File>>(&self.archive as *const zip::ZipArchive<File>) }.by_name(local_path.to_str().unwrap())?;
+            let mut archive = self.archive.borrow_mut();
+            let mut file = archive.by_name(local_path.to_str().unwrap())?;
             let mut out = Vec::new();
             match file.read_to_end(&mut out) {
                 Ok(_) => Ok(out),
@@ -153,11 +197,946 @@ mod tests {
 
     #[test]
     fn orbit_test() {
-        let mut discovery = orbit::DiscoverSystem::new(StandardLoader {}, ConflictHandler::NoRoot);
-        assert!(discovery.discover_in_root("/mnt/c/Users/coe_a/Downloads").len() == 0);
-        discovery.tree.walk_paths(|n, _| {
+        let root = test_root("orbit_test");
+        write_test_file(&root, "fighter/mario/model.bin", b"mario");
+        write_test_file(&root, "ui/param.prc", b"param");
+        let mut discovery = orbit::LaunchPad::new(StandardLoader {}, ConflictHandler::NoRoot);
+        assert!(discovery.discover_in_root(&root).unwrap().is_empty());
+        let mut file_count = 0;
+        discovery.tree().walk_paths(|n, entry_type| {
             println!("{}", n.full_path().display());
+            if entry_type.is_file() {
+                file_count += 1;
+            }
         });
-        panic!();
+        assert!(file_count == 2);
+    }
+
+    fn write_test_zip(path: &std::path::Path, files: &[(&str, &[u8])]) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in files.iter() {
+            writer.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn layered_orbit_test() {
+        let root = test_root("layered_orbit_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("ui/param.prc", b"physical"), ("ui/msg.msbt", b"physical")]);
+        write_test_file(&root, "mods/ui/param.prc", b"patch");
+        write_test_file(&root, "user/ui/param.prc", b"user");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        let mut user = tree::Tree::new(StandardLoader);
+        user.insert_file(root.join("user"), "ui/param.prc");
+
+        let mut layered = orbit::LayeredOrbit::new();
+        layered.push_layer("physical", tree::Tree::new(ZipLoader::new(&archive)));
+        layered.push_layer("patch", launchpad.into_tree());
+        assert!(layered.push_layer("user", user) == 2);
+
+        assert!(layered.load("ui/param.prc").unwrap() == b"user");
+        assert!(layered.load_from(1, "ui/param.prc").unwrap() == b"patch");
+        assert!(layered.load("ui/msg.msbt").unwrap() == b"physical");
+        assert!(matches!(layered.load("ui/missing.prc"), Err(orbit::LayerError::NotFound(_))));
+        assert!(layered.query_actual_path("ui/param.prc").unwrap() == root.join("user/ui/param.prc"));
+        assert!(layered.query_max_filesize("ui/param.prc") == Some(5));
+        assert!(layered.query_max_filesize("ui/msg.msbt").is_none());
+        assert!(layered.layer_index("patch") == Some(1));
+        assert!(layered.contains("ui/param.prc"));
+
+        let mut walked = Vec::new();
+        layered.walk_layers(|index, node, entry_type| {
+            if entry_type.is_file() {
+                walked.push((index, node.get_root().to_path_buf()));
+            }
+        });
+        assert!(walked == vec![(2, root.join("user")), (1, root.join("mods"))]);
+
+        let orbit = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot)
+            .launch(ZipLoader::new(&archive), tree::Tree::new(StandardLoader))
+            .into_layered();
+        assert!(orbit.layer_names() == vec!["physical", "patch", "virtual"]);
+        assert!(orbit.load("ui/param.prc").unwrap() == b"physical");
+    }
+
+    #[test]
+    fn open_test() {
+        let root = test_root("open_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("ui/param.prc", b"physical"), ("ui/msg.msbt", b"physical message")]);
+        write_test_file(&root, "mods/ui/param.prc", b"patched param");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        let orbit = launchpad.launch(ZipLoader::new(&archive), tree::Tree::new(StandardLoader));
+
+        let mut reader = orbit.open("ui/param.prc").unwrap();
+        let mut data = String::new();
+        reader.seek(SeekFrom::Start(8)).unwrap();
+        reader.read_to_string(&mut data).unwrap();
+        assert!(data == "param");
+
+        let mut reader = orbit.open("ui/msg.msbt").unwrap();
+        let mut data = Vec::new();
+        reader.seek(SeekFrom::End(-7)).unwrap();
+        reader.read_to_end(&mut data).unwrap();
+        assert!(data == b"message");
+    }
+
+    #[test]
+    fn read_range_test() {
+        let root = test_root("read_range_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("ui/param.prc", b"physical"), ("ui/msg.msbt", b"MSGSTDBN")]);
+        write_test_file(&root, "mods/ui/param.prc", b"PRCFpatch");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        let orbit = launchpad.launch(ZipLoader::new(&archive), tree::Tree::new(StandardLoader));
+
+        let mut magic = [0u8; 4];
+        assert!(orbit.read_range("ui/param.prc", 0, &mut magic).unwrap() == 4);
+        assert!(&magic == b"PRCF");
+        assert!(orbit.read_range("ui/msg.msbt", 3, &mut magic).unwrap() == 4);
+        assert!(&magic == b"STDB");
+
+        let mut tail = [0u8; 8];
+        assert!(orbit.read_range("ui/param.prc", 4, &mut tail).unwrap() == 5);
+        assert!(&tail[..5] == b"patch");
+        assert!(orbit.read_range("ui/msg.msbt", 6, &mut tail).unwrap() == 2);
+        assert!(orbit.read_range("ui/msg.msbt", 16, &mut tail).unwrap() == 0);
+    }
+
+    #[test]
+    fn load_into_test() {
+        let root = test_root("load_into_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("ui/param.prc", b"physical"), ("ui/msg.msbt", b"physical message")]);
+        write_test_file(&root, "mods/ui/param.prc", b"patch");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        let orbit = launchpad.launch(ZipLoader::new(&archive), tree::Tree::new(StandardLoader));
+
+        let mut buf = vec![0u8; orbit.resolve("ui/param.prc").unwrap().size.unwrap()];
+        assert!(orbit.load_into("ui/param.prc", &mut buf).unwrap() == 5);
+        assert!(buf == b"patch");
+        let mut buf = vec![0u8; orbit.physical_filesize("ui/param.prc").unwrap()];
+        assert!(orbit.load_into_physical("ui/param.prc", &mut buf).unwrap() == 8);
+        assert!(buf == b"physical");
+
+        match orbit.load_into("ui/msg.msbt", &mut buf) {
+            Err(orbit::Error::BufferTooSmall { size, capacity, .. }) => assert!(size == 16 && capacity == 8),
+            _ => panic!("Expected load_into to fail with a buffer that is too small")
+        }
+        let mut buf = [0u8; 4];
+        assert!(matches!(orbit.load_into("ui/param.prc", &mut buf), Err(orbit::Error::BufferTooSmall { size: 5, capacity: 4, .. })));
+    }
+
+    #[test]
+    fn content_cache_test() {
+        let root = test_root("content_cache_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("ui/param.prc", b"physical"), ("ui/msg.msbt", b"physical message")]);
+        write_test_file(&root, "mods/ui/param.prc", b"patch");
+        write_test_file(&root, "virtual/ui/gen.bin", b"virtual");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        let mut orbit = launchpad.launch(ZipLoader::new(&archive), tree::Tree::new(StandardLoader));
+        orbit.insert_virtual_file(root.join("virtual"), "ui/gen.bin");
+        orbit.enable_cache(orbit::ContentCache::new(20));
+
+        assert!(orbit.load("ui/param.prc").unwrap() == b"patch");
+        write_test_file(&root, "mods/ui/param.prc", b"changed");
+        assert!(orbit.load("ui/param.prc").unwrap() == b"patch");
+        assert!(orbit.load("ui/gen.bin").unwrap() == b"virtual");
+        assert!(orbit.load("ui/gen.bin").unwrap() == b"virtual");
+        let stats = orbit.cache_stats().unwrap();
+        assert!(stats.hits == 1 && stats.misses == 3 && stats.count == 1 && stats.size == 5);
+
+        // Every way of reading a cached file is served by the cache, and equivalent paths share an entry
+        let mut data = Vec::new();
+        orbit.open("ui/param.prc").unwrap().read_to_end(&mut data).unwrap();
+        assert!(data == b"patch");
+        let mut buf = [0u8; 5];
+        assert!(orbit.read_range("ui/param.prc", 2, &mut buf).unwrap() == 3 && &buf[..3] == b"tch");
+        assert!(orbit.load_into("ui/./param.prc", &mut buf).unwrap() == 5 && &buf == b"patch");
+        assert!(orbit.cache_stats().unwrap().hits == 4);
+
+        // Filling the budget evicts the least recently used file
+        assert!(orbit.load("ui/msg.msbt").unwrap() == b"physical message");
+        assert!(orbit.load("ui/param.prc").unwrap() == b"changed");
+        assert!(orbit.cache_stats().unwrap().evictions == 2);
+
+        assert!(orbit.remove_patch_path("ui/param.prc").is_some());
+        assert!(orbit.load("ui/param.prc").unwrap() == b"physical");
+        assert!(orbit.cache_stats().unwrap().size == 8);
+
+        // Modifying a tree directly can't leave stale data in the cache
+        write_test_file(&root, "virtual/ui/param.prc", b"virtual param");
+        orbit.virt_mut().insert_file(root.join("virtual"), "ui/param.prc");
+        assert!(orbit.load("ui/param.prc").unwrap() == b"virtual param");
+        assert!(orbit.cache_stats().unwrap().count == 0);
+    }
+
+    #[test]
+    fn resolve_test() {
+        let root = test_root("resolve_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("ui/param.prc", b"physical"), ("ui/msg.msbt", b"physical message")]);
+        write_test_file(&root, "mods/ui/param.prc", b"patch");
+        write_test_file(&root, "virtual/ui/gen.bin", b"virtual");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        let mut orbit = launchpad.launch(ZipLoader::new(&archive), tree::Tree::new(StandardLoader));
+        orbit.insert_virtual_file(root.join("virtual"), "ui/gen.bin");
+
+        let resolved = orbit.resolve("ui/param.prc").unwrap();
+        assert!(resolved.layer == orbit::LayerKind::Patch);
+        assert!(resolved.root_path == root.join("mods"));
+        assert!(resolved.actual_path == Some(root.join("mods/ui/param.prc")));
+        assert!(resolved.entry_type == Some(FileEntryType::File));
+        assert!(resolved.size == Some(5));
+
+        let resolved = orbit.resolve("ui/gen.bin").unwrap();
+        assert!(resolved.layer == orbit::LayerKind::Virtual && resolved.size == Some(7));
+
+        let resolved = orbit.resolve("ui/msg.msbt").unwrap();
+        assert!(resolved.layer == orbit::LayerKind::Physical);
+        assert!(resolved.root_path == std::path::Path::new("") && resolved.size == Some(16));
+
+        assert!(orbit.resolve("ui").unwrap().entry_type == Some(FileEntryType::Directory));
+        assert!(orbit.resolve("ui/missing.prc").is_none());
+    }
+
+    #[test]
+    fn whiteout_test() {
+        let root = test_root("whiteout_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("ui/param.prc", b"physical"), ("ui/msg.msbt", b"physical message")]);
+        write_test_file(&root, "mods/ui/param.prc", b"patch");
+        write_test_file(&root, "mods/ui/.wh.msg.msbt", b"");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        assert!(launchpad.tree().contains_whiteout("ui/msg.msbt"));
+        let orbit = launchpad.launch(ZipLoader::new(&archive), tree::Tree::new(StandardLoader));
+
+        assert!(matches!(orbit.load("ui/msg.msbt"), Err(orbit::Error::NotFound(_))));
+        assert!(matches!(orbit.open("ui/msg.msbt"), Err(orbit::Error::NotFound(_))));
+        assert!(orbit.load_physical("ui/msg.msbt").unwrap() == b"physical message");
+        assert!(!orbit.contains("ui/msg.msbt"));
+        assert!(orbit.resolve("ui/msg.msbt").is_none());
+        assert!(orbit.query_max_filesize("ui/msg.msbt").is_none());
+        let children = orbit.get_children("ui");
+        assert!(children.len() == 1 && children.contains(std::path::Path::new("ui/param.prc")));
+        assert!(orbit.load("ui/param.prc").unwrap() == b"patch");
+
+        let mut whiteouts = Vec::new();
+        orbit.patch().walk_whiteouts(|node| whiteouts.push(node.full_path()));
+        assert!(whiteouts == vec![root.join("mods/ui/msg.msbt")]);
+
+        // Entries inserted underneath a whiteout turn it into an opaque directory
+        let mut patch = tree::Tree::new(StandardLoader);
+        patch.insert_whiteout(root.join("mods"), "ui");
+        assert!(patch.insert_file(root.join("mods"), "ui/param.prc").is_none());
+        assert!(patch.load("ui/param.prc").unwrap().unwrap() == b"patch");
+        assert!(!patch.contains_whiteout("ui") && patch.is_opaque("ui"));
+        assert!(patch.is_masked("ui/msg.msbt"));
+
+        // A whiteout which can fall back to a file is kept instead
+        let candidates = vec![
+            tree::Candidate { root: root.join("mods"), whiteout: true, mount: std::path::PathBuf::new() },
+            tree::Candidate { root: root.join("base"), whiteout: false, mount: std::path::PathBuf::new() }
+        ];
+        patch.insert_whiteout(root.join("mods"), "stage");
+        assert!(patch.set_candidates("stage", candidates, 0));
+        let blocked = patch.insert_file(root.join("other"), "stage/param.prc").unwrap();
+        assert!(blocked == (root.join("mods"), std::path::PathBuf::from("stage")));
+        assert!(patch.contains_whiteout("stage") && !patch.contains_path("stage/param.prc"));
+        patch.remove_paths_by_root(root.join("mods"));
+        assert!(patch.get_root_for_path("stage") == Some(root.join("base")) && !patch.contains_whiteout("stage"));
+    }
+
+    #[test]
+    fn transformer_test() {
+        let root = test_root("transformer_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("ui/param.prc", b"physical"), ("ui/msg.msbt", b"physical message")]);
+        write_test_file(&root, "mods/ui/param.prc", b"patch");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        let mut orbit = launchpad.launch(ZipLoader::new(&archive), tree::Tree::new(StandardLoader));
+        orbit.register_transformer("ui/param.prc", |_, mut data| {
+            data.extend_from_slice(b"+one");
+            Ok(data)
+        });
+        orbit.register_transformer("ui/param.prc", |_, mut data| {
+            data.extend_from_slice(b"+two");
+            Ok(data)
+        });
+        orbit.register_transformer("ui/msg.msbt", |path, _| {
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Can't transform '{}'", path.display())))
+        });
+
+        assert!(orbit.load("ui/param.prc").unwrap() == b"patch+one+two");
+        assert!(orbit.load("ui/./param.prc").unwrap() == b"patch+one+two");
+        let mut buf = [0u8; 8];
+        assert!(orbit.read_range("ui/param.prc", 5, &mut buf).unwrap() == 8);
+        assert!(&buf == b"+one+two");
+        assert!(orbit.resolve("ui/param.prc").unwrap().transformers == 2);
+        assert!(orbit.query_filesize("ui/param.prc") == Some(13) && orbit.query_max_filesize("ui/param.prc") == Some(13));
+        let mut buf = vec![0u8; orbit.query_filesize("ui/param.prc").unwrap()];
+        assert!(orbit.load_into("ui/param.prc", &mut buf).unwrap() == 13);
+        assert!(orbit.read_range("ui/param.prc", u64::MAX, &mut buf).unwrap() == 0);
+        assert!(matches!(orbit.load("ui/msg.msbt"), Err(orbit::Error::Virtual(_))));
+
+        assert!(orbit.remove_transformers("ui/msg.msbt") == 1);
+        assert!(orbit.load("ui/msg.msbt").unwrap() == b"physical message");
+
+        // Transformed output has its own cache flag, and measuring it neither fills the cache nor counts towards its statistics
+        orbit.enable_cache(orbit::ContentCache::new(1024));
+        assert!(orbit.query_filesize("ui/param.prc") == Some(13));
+        assert!(orbit.cache_stats().unwrap() == orbit::CacheStats::default());
+        assert!(orbit.load("ui/param.prc").unwrap() == b"patch+one+two");
+        assert!(orbit.query_filesize("ui/param.prc") == Some(13));
+        let stats = orbit.cache_stats().unwrap();
+        assert!(stats.count == 1 && stats.hits == 0 && stats.misses == 1);
+        let mut cache = orbit::ContentCache::new(1024);
+        cache.cache_transformed(false);
+        orbit.enable_cache(cache);
+        assert!(orbit.load("ui/param.prc").unwrap() == b"patch+one+two");
+        assert!(orbit.cache_stats().unwrap().count == 0);
+    }
+
+    #[test]
+    fn nested_orbit_test() {
+        let root = test_root("nested_orbit_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("ui/param.prc", b"physical"), ("ui/msg.msbt", b"physical message"), ("ui/font.bin", b"font")]);
+        write_test_file(&root, "mods/ui/param.prc", b"patch");
+        write_test_file(&root, "mods/ui/msg.msbt", b"patched message");
+        write_test_file(&root, "profile/ui/msg.msbt", b"profile message");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        let base = launchpad.launch(ZipLoader::new(&archive), tree::Tree::new(StandardLoader));
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("profile")).unwrap().is_empty());
+        let profile = launchpad.launch(base, tree::Tree::new(StandardLoader));
+
+        assert!(profile.load("ui/msg.msbt").unwrap() == b"profile message");
+        assert!(profile.load("ui/param.prc").unwrap() == b"patch");
+        assert!(profile.load("ui/font.bin").unwrap() == b"font");
+        assert!(profile.physical_filesize("ui/param.prc") == Some(5));
+        assert!(profile.query_max_filesize("ui/msg.msbt") == Some(15));
+        assert!(profile.resolve("ui/param.prc").unwrap().actual_path == Some(root.join("mods/ui/param.prc")));
+        assert!(matches!(profile.load("ui/missing.bin"), Err(orbit::Error::NotFound(_))));
+
+        let mut buf = [0u8; 4];
+        assert!(profile.load_into("ui/font.bin", &mut buf).unwrap() == 4);
+        assert!(matches!(profile.load_into("ui/param.prc", &mut buf), Err(orbit::Error::BufferTooSmall { size: 5, .. })));
+    }
+
+    #[test]
+    fn strict_conflict_test() {
+        let root = test_root("strict_conflict_test");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_b/ui/param.prc", b"b");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::Strict);
+        assert!(launchpad.discover_in_root(root.join("mod_a")).unwrap().is_empty());
+        match launchpad.discover_in_root(root.join("mod_b")) {
+            Err(orbit::DiscoveryError::Conflict { path, existing }) => {
+                assert!(path == root.join("mod_b/ui/param.prc"));
+                assert!(existing == root.join("mod_a/ui/param.prc"));
+            },
+            _ => panic!("Expected a strict conflict error")
+        }
+    }
+
+    #[test]
+    fn no_root_rollback_test() {
+        let root = test_root("no_root_rollback_test");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_b/readme.txt", b"readme");
+        write_test_file(&root, "mod_b/fighter/mario/model.bin", b"mario");
+        write_test_file(&root, "mod_b/ui/param.prc", b"b");
+        write_test_file(&root, "mod_b/ui/.wh.layout.arc", b"");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        launchpad.collecting(|path| path == std::path::Path::new("readme.txt"));
+        assert!(launchpad.discover_in_root(root.join("mod_a")).unwrap().is_empty());
+        let conflicts = launchpad.discover_in_root(root.join("mod_b")).unwrap();
+        assert!(conflicts.len() == 1);
+        match &conflicts[0] {
+            orbit::ConflictKind::RootConflict { root: rejected, existing_root, local } => {
+                assert!(*rejected == root.join("mod_b"));
+                assert!(*existing_root == root.join("mod_a"));
+                assert!(local == std::path::Path::new("ui/param.prc"));
+            },
+            _ => panic!("Expected a root conflict")
+        }
+
+        let tree = launchpad.tree();
+        assert!(!tree.contains_path("fighter"));
+        assert!(!tree.contains_whiteout("ui/layout.arc"));
+        assert!(tree.get_root_for_path("ui/param.prc") == Some(root.join("mod_a")));
+        assert!(launchpad.collected_paths().is_empty());
+    }
+
+    #[test]
+    fn priority_conflict_test() {
+        let root = test_root("priority_conflict_test");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_b/ui/param.prc", b"b");
+        write_test_file(&root, "mod_c/ui/param.prc", b"c");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::Priority);
+        launchpad.set_root_priority(root.join("mod_c"), 10);
+        assert!(launchpad.discover_in_root_with_priority(root.join("mod_b"), 5).unwrap().is_empty());
+        let conflicts = launchpad.discover_in_root_with_priority(root.join("mod_a"), 1).unwrap();
+        match conflicts.as_slice() {
+            [orbit::ConflictKind::StandardConflict { error_root, source_root, local }] => {
+                assert!(*error_root == root.join("mod_a"));
+                assert!(*source_root == root.join("mod_b"));
+                assert!(local == std::path::Path::new("ui/param.prc"));
+            },
+            _ => panic!("Expected a single standard conflict")
+        }
+        let conflicts = launchpad.discover_in_root(root.join("mod_c")).unwrap();
+        match conflicts.as_slice() {
+            [orbit::ConflictKind::StandardConflict { error_root, source_root, .. }] => {
+                assert!(*error_root == root.join("mod_b"));
+                assert!(*source_root == root.join("mod_c"));
+            },
+            _ => panic!("Expected a single standard conflict")
+        }
+        assert!(launchpad.tree().get_root_for_path("ui/param.prc") == Some(root.join("mod_c")));
+    }
+
+    #[test]
+    fn conflict_resolver_test() {
+        use orbit::{ConflictAction, ConflictCandidate};
+
+        let root = test_root("conflict_resolver_test");
+        write_test_file(&root, "mod_a/textures/grass.bin", b"a");
+        write_test_file(&root, "mod_a/fighter/mario/model.bin", b"a");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_b/textures/grass.bin", b"b");
+        write_test_file(&root, "mod_b/ui/param.prc", b"b");
+        write_test_file(&root, "mod_c/textures/grass.bin", b"c");
+        write_test_file(&root, "mod_c/fighter/mario/model.bin", b"c");
+
+        let resolver = |_: &ConflictCandidate, incoming: &ConflictCandidate| {
+            if incoming.local.starts_with("textures") {
+                ConflictAction::Replace
+            } else if incoming.local.starts_with("fighter") {
+                ConflictAction::RejectRoot
+            } else {
+                ConflictAction::KeepExisting
+            }
+        };
+        let mut launchpad = orbit::LaunchPad::with_resolver(StandardLoader, resolver);
+        assert!(launchpad.discover_in_root(root.join("mod_a")).unwrap().is_empty());
+        assert!(launchpad.discover_in_root(root.join("mod_b")).unwrap().len() == 2);
+        assert!(launchpad.tree().get_root_for_path("textures/grass.bin") == Some(root.join("mod_b")));
+        assert!(launchpad.tree().get_root_for_path("ui/param.prc") == Some(root.join("mod_a")));
+
+        // mod_c may replace the texture before it is rejected, so the replaced texture must be restored
+        let conflicts = launchpad.discover_in_root(root.join("mod_c")).unwrap();
+        assert!(matches!(conflicts.as_slice(), [orbit::ConflictKind::RootConflict { .. }]));
+        assert!(launchpad.tree().get_root_for_path("textures/grass.bin") == Some(root.join("mod_b")));
+
+        launchpad.set_resolver(ConflictHandler::Strict);
+        assert!(launchpad.discover_in_root(root.join("mod_c")).is_err());
+        assert!(launchpad.tree().get_root_for_path("textures/grass.bin") == Some(root.join("mod_b")));
+    }
+
+    #[test]
+    fn duplicate_test() {
+        let root = test_root("duplicate_test");
+        write_test_file(&root, "mod_a/common/shared.bin", b"shared");
+        write_test_file(&root, "mod_b/common/shared.bin", b"shared");
+        write_test_file(&root, "mod_b/ui/param.prc", b"b");
+        write_test_file(&root, "mod_c/common/shared.bin", b"SHARED");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        launchpad.accepting_duplicates(true);
+        assert!(launchpad.discover_in_root(root.join("mod_a")).unwrap().is_empty());
+        match launchpad.discover_in_root(root.join("mod_b")).unwrap().as_slice() {
+            [orbit::ConflictKind::Duplicate { root: duplicate, existing_root, local }] => {
+                assert!(*duplicate == root.join("mod_b"));
+                assert!(*existing_root == root.join("mod_a"));
+                assert!(local == std::path::Path::new("common/shared.bin"));
+            },
+            _ => panic!("Expected a single duplicate")
+        }
+        assert!(launchpad.tree().contains_path("ui/param.prc"));
+        assert!(launchpad.tree().get_root_for_path("common/shared.bin") == Some(root.join("mod_a")));
+
+        let conflicts = launchpad.discover_in_root(root.join("mod_c")).unwrap();
+        assert!(matches!(conflicts.as_slice(), [orbit::ConflictKind::RootConflict { .. }]));
+    }
+
+    #[test]
+    fn candidate_test() {
+        let root = test_root("candidate_test");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_b/ui/param.prc", b"b");
+        write_test_file(&root, "mod_c/ui/param.prc", b"c");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::Priority);
+        assert!(launchpad.discover_in_root_with_priority(root.join("mod_b"), 5).unwrap().is_empty());
+        assert!(launchpad.discover_in_root_with_priority(root.join("mod_a"), 1).unwrap().len() == 1);
+        assert!(launchpad.discover_in_root_with_priority(root.join("mod_c"), 10).unwrap().len() == 1);
+        let roots: Vec<_> = launchpad.tree().candidates("ui/param.prc").iter().map(|candidate| candidate.root.clone()).collect();
+        assert!(roots == vec![root.join("mod_c"), root.join("mod_b"), root.join("mod_a")]);
+        assert!(launchpad.tree().active_candidate("ui/param.prc") == Some(0));
+
+        let mut tree = launchpad.into_tree();
+        assert!(tree.set_active_candidate("ui/param.prc", 2));
+        assert!(tree.load("ui/param.prc").unwrap().unwrap() == b"a");
+        assert!(!tree.set_active_candidate("ui/param.prc", 3));
+
+        // Removing the active root falls back to the highest priority candidate left
+        assert!(tree.remove_paths_by_root(root.join("mod_a")).is_empty());
+        assert!(tree.load("ui/param.prc").unwrap().unwrap() == b"c");
+        tree.remove_paths_by_root(root.join("mod_c"));
+        assert!(tree.load("ui/param.prc").unwrap().unwrap() == b"b");
+        assert!(tree.remove_paths_by_root(root.join("mod_b")) == vec![std::path::PathBuf::from("ui/param.prc")]);
+        assert!(!tree.contains_path("ui/param.prc"));
+    }
+
+    #[test]
+    fn conflict_choices_test() {
+        let root = test_root("conflict_choices_test");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_b/ui/param.prc", b"b");
+
+        let mut choices = orbit::ConflictChoices::new();
+        choices.set("ui/param.prc", root.join("mod_b"));
+        choices.set("ui/layout.arc", root.join("mod_gone"));
+        choices.save(root.join("choices.txt")).unwrap();
+        let choices = orbit::ConflictChoices::load(root.join("choices.txt")).unwrap();
+        assert!(choices.len() == 2);
+        assert!("ui/param.prc".parse::<orbit::ConflictChoices>().is_err());
+        assert!("ui/param.prc\tC:\\mods".parse::<orbit::ConflictChoices>().is_err());
+
+        // Separators, comments and escapes inside of paths survive a round trip
+        let mut escaped = orbit::ConflictChoices::new();
+        escaped.set("#ui/odd\tname\\.prc", "mods\nb");
+        assert!(escaped.to_text().unwrap().parse::<orbit::ConflictChoices>().unwrap() == escaped);
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            escaped.set(std::ffi::OsStr::from_bytes(b"ui/\xff.prc"), "mods");
+            assert!(matches!(escaped.to_text(), Err(orbit::ChoicesError::NonUtf8Path(_))));
+        }
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        launchpad.set_choices(choices);
+        assert!(launchpad.discover_in_root(root.join("mod_a")).unwrap().is_empty());
+        let conflicts = launchpad.discover_in_root(root.join("mod_b")).unwrap();
+        assert!(matches!(conflicts.as_slice(), [orbit::ConflictKind::StandardConflict { .. }]));
+        assert!(launchpad.tree().get_root_for_path("ui/param.prc") == Some(root.join("mod_b")));
+        assert!(launchpad.stale_choices() == vec![(std::path::PathBuf::from("ui/layout.arc"), root.join("mod_gone"))]);
+    }
+
+    #[test]
+    fn conflict_report_test() {
+        let root = test_root("conflict_report_test");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_a/ui/layout.arc", b"a");
+        write_test_file(&root, "mod_a/common/shared.bin", b"shared");
+        write_test_file(&root, "mod_b/ui/param.prc", b"b");
+        write_test_file(&root, "mod_b/ui/layout.arc", b"b");
+        write_test_file(&root, "mod_b/common/shared.bin", b"shared");
+        write_test_file(&root, "mod_c/ui/param.prc", b"c");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::First);
+        launchpad.accepting_duplicates(true);
+        launchpad.discover_in_root(root.join("mod_a")).unwrap();
+        launchpad.discover_in_root(root.join("mod_b")).unwrap();
+        launchpad.set_resolver(ConflictHandler::NoRoot);
+        launchpad.discover_in_root(root.join("mod_c")).unwrap();
+
+        let report = launchpad.conflict_report();
+        assert!(report.summary() == orbit::ConflictSummary {
+            conflicts: 2,
+            type_conflicts: 0,
+            duplicates: 1,
+            rejected_roots: 1,
+            root_pairs: 2,
+            paths: 3
+        });
+        let pairs = report.pairs();
+        assert!(pairs[0].winner == root.join("mod_a") && pairs[0].loser == root.join("mod_b"));
+        assert!(pairs[0].entries.len() == 3);
+        assert!(report.paths_between(root.join("mod_a"), root.join("mod_c")) == vec![std::path::Path::new("ui/param.prc")]);
+        let text = report.to_string();
+        assert!(text.starts_with("2 conflicts, 0 type conflicts, 1 duplicates and 1 rejected roots across 2 root pairs"));
+        assert!(text.contains("common/shared.bin (duplicate)"));
+
+        #[cfg(feature = "serde")]
+        assert!(report.to_json().unwrap().contains("\"rejected_roots\": 1"));
+    }
+
+    #[test]
+    fn conflict_matrix_test() {
+        let root = test_root("conflict_matrix_test");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_a/ui/layout.arc", b"a");
+        write_test_file(&root, "mod_b/ui/param.prc", b"b");
+        write_test_file(&root, "mod_c/ui/layout.arc", b"c");
+        write_test_file(&root, "mod_c/ui/param.prc", b"c");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::Last);
+        for name in ["mod_a", "mod_b", "mod_c"].iter() {
+            launchpad.discover_in_root(root.join(name)).unwrap();
+        }
+
+        let matrix = launchpad.conflict_matrix();
+        assert!(matrix.roots() == [root.join("mod_a"), root.join("mod_b"), root.join("mod_c")]);
+        assert!(matrix.get(0, 1) == [std::path::PathBuf::from("ui/param.prc")]);
+        assert!(matrix.get(1, 0) == matrix.get(0, 1));
+        assert!(matrix.between(root.join("mod_c"), root.join("mod_a")) == [std::path::PathBuf::from("ui/layout.arc")]);
+        assert!(matrix.between(root.join("mod_b"), root.join("mod_c")).len() == 1);
+        assert!(matrix.iter().count() == 3);
+
+        let dot = matrix.to_dot();
+        assert!(dot.starts_with("graph conflicts {"));
+        assert!(dot.contains(&format!("\"{}\" -- \"{}\" [label=\"1\", weight=1];", root.join("mod_a").display(), root.join("mod_b").display())));
+    }
+
+    #[test]
+    fn type_conflict_test() {
+        use orbit::{ConflictAction, ConflictCandidate, ConflictKind};
+
+        let root = test_root("type_conflict_test");
+        write_test_file(&root, "mod_a/ui/param", b"a");
+        write_test_file(&root, "mod_b/ui/param/x.bin", b"b");
+        write_test_file(&root, "mod_b/ui/other.bin", b"b");
+        write_test_file(&root, "mod_c/ui/param/y.bin", b"c");
+        write_test_file(&root, "mod_d/ui/param", b"d");
+        write_test_file(&root, "mod_d/ui/other.bin", b"d");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::First);
+        launchpad.discover_in_root(root.join("mod_a")).unwrap();
+        match launchpad.discover_in_root(root.join("mod_b")).unwrap().as_slice() {
+            [ConflictKind::TypeConflict { error_root, source_root, local, error_type }] => {
+                assert!(*error_root == root.join("mod_b"));
+                assert!(*source_root == root.join("mod_a"));
+                assert!(local == std::path::Path::new("ui/param"));
+                assert!(*error_type == FileEntryType::Directory);
+            },
+            _ => panic!("Expected a single type conflict")
+        }
+        assert!(launchpad.tree().get_entry_type("ui/param") == Some(FileEntryType::File));
+        assert!(!launchpad.tree().contains_path("ui/param/x.bin"));
+        assert!(launchpad.tree().contains_path("ui/other.bin"));
+
+        launchpad.set_resolver(ConflictHandler::Last);
+        launchpad.discover_in_root(root.join("mod_c")).unwrap();
+        assert!(launchpad.tree().get_entry_type("ui/param") == Some(FileEntryType::Directory));
+        assert!(launchpad.tree().contains_path("ui/param/y.bin"));
+
+        // mod_d may replace the directory before it is rejected, so the directory must be restored
+        launchpad.set_resolver(|_: &ConflictCandidate, incoming: &ConflictCandidate| {
+            if incoming.entry_type == FileEntryType::File && incoming.local == std::path::Path::new("ui/param") {
+                ConflictAction::Replace
+            } else {
+                ConflictAction::RejectRoot
+            }
+        });
+        match launchpad.discover_in_root(root.join("mod_d")).unwrap().as_slice() {
+            [ConflictKind::RootConflict { existing_root, .. }] => assert!(*existing_root == root.join("mod_b")),
+            _ => panic!("Expected a root conflict")
+        }
+        assert!(launchpad.tree().load("ui/param/y.bin").unwrap().unwrap() == b"c");
+        assert!(launchpad.tree().directory_roots("ui/param") == [root.join("mod_c")]);
+        assert!(launchpad.tree().directory_roots("ui") == [root.join("mod_a"), root.join("mod_b"), root.join("mod_c")]);
+
+        // The directory is reported as coming from the root that provided it, and not from some root with a file underneath it
+        write_test_file(&root, "mod_e/ui/param", b"e");
+        launchpad.set_resolver(ConflictHandler::First);
+        match launchpad.discover_in_root(root.join("mod_e")).unwrap().as_slice() {
+            [ConflictKind::TypeConflict { source_root, error_type, .. }] => {
+                assert!(*source_root == root.join("mod_c"));
+                assert!(*error_type == FileEntryType::File);
+            },
+            _ => panic!("Expected a single type conflict")
+        }
+        let mut tree = launchpad.into_tree();
+        tree.remove_paths_by_root(root.join("mod_a"));
+        assert!(tree.directory_roots("ui") == [root.join("mod_b"), root.join("mod_c")]);
+    }
+
+    #[test]
+    fn opaque_test() {
+        let root = test_root("opaque_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("ui/param.prc", b"physical"), ("ui/layout/a.bin", b"physical"), ("ui/layout/b.bin", b"physical")]);
+        write_test_file(&root, "mods/ui/layout/a.bin", b"patch");
+        write_test_file(&root, format!("mods/ui/layout/{}", orbit::OPAQUE_MARKER), b"");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        assert!(launchpad.tree().is_opaque("ui/layout"));
+        assert!(launchpad.tree().opaque_roots("ui/layout") == [root.join("mods")]);
+        assert!(!launchpad.tree().contains_path(format!("ui/layout/{}", orbit::OPAQUE_MARKER)));
+        let patch = launchpad.into_tree();
+
+        // A directory marked by one root and created by another stops being opaque when the marking root is removed
+        write_test_file(&root, "base/ui/layout/c.bin", b"base");
+        write_test_file(&root, format!("marker/ui/layout/{}", orbit::OPAQUE_MARKER), b"");
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("base")).unwrap().is_empty());
+        assert!(launchpad.discover_in_root(root.join("marker")).unwrap().is_empty());
+        let mut marked = launchpad.into_tree();
+        assert!(marked.is_masked("ui/layout/b.bin"));
+        // The marking root's directory replaces the one from base as well, until the marking root is removed
+        assert!(!marked.contains_path("ui/layout/c.bin"));
+        assert!(marked.load("ui/layout/c.bin").unwrap().is_none());
+        assert!(marked.get_children("ui/layout").is_empty());
+        assert!(marked.remove_paths_by_root(root.join("marker")).is_empty());
+        assert!(!marked.is_opaque("ui/layout") && !marked.is_masked("ui/layout/b.bin"));
+        assert!(marked.load("ui/layout/c.bin").unwrap().unwrap() == b"base");
+        assert!(marked.get_children("ui/layout").len() == 1);
+
+        let mut physical = tree::Tree::new(ZipLoader::new(&archive));
+        for path in ["ui/param.prc", "ui/layout/a.bin", "ui/layout/b.bin"].iter() {
+            physical.insert_file("", path);
+        }
+        let mut layered = orbit::LayeredOrbit::new();
+        layered.push_layer("physical", physical);
+        layered.push_layer("patch", patch);
+        assert!(layered.load("ui/layout/a.bin").unwrap() == b"patch");
+        assert!(matches!(layered.load("ui/layout/b.bin"), Err(orbit::LayerError::NotFound(_))));
+        assert!(!layered.contains("ui/layout/b.bin"));
+        assert!(layered.load("ui/param.prc").unwrap() == b"physical");
+        let children = layered.get_children("ui/layout");
+        assert!(children.len() == 1 && children.contains(std::path::Path::new("ui/layout/a.bin")));
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        let orbit = launchpad.launch(ZipLoader::new(&archive), tree::Tree::new(StandardLoader));
+        assert!(matches!(orbit.load("ui/layout/b.bin"), Err(orbit::Error::NotFound(_))));
+        assert!(orbit.resolve("ui/layout/b.bin").is_none());
+        assert!(orbit.load("ui/param.prc").unwrap() == b"physical");
+    }
+
+    #[test]
+    fn ignore_file_test() {
+        let root = test_root("ignore_file_test");
+        write_test_file(&root, format!("mod_a/{}", orbit::IGNORE_FILE), b"# mod_a's extras\n*.png\npreview/\n!ui/icon.png\n");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_a/ui/icon.png", b"a");
+        write_test_file(&root, "mod_a/ui/other.png", b"a");
+        write_test_file(&root, "mod_a/preview/main.jpg", b"a");
+        write_test_file(&root, "mod_a/README.md", b"a");
+        write_test_file(&root, "mod_b/ui/other.png", b"b");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        launchpad.ignoring_globs(&["README.md"]).unwrap();
+        launchpad.reporting_ignored(true);
+        launchpad.add_collector("everything", |_: &std::path::Path| true, true);
+        assert!(launchpad.discover_in_root(root.join("mod_a")).unwrap().is_empty());
+        assert!(launchpad.discover_in_root(root.join("mod_b")).unwrap().is_empty());
+
+        let tree = launchpad.tree();
+        assert!(tree.contains_path("ui/param.prc"));
+        assert!(tree.contains_path("ui/icon.png"));
+        assert!(!tree.contains_path(orbit::IGNORE_FILE));
+        assert!(!tree.contains_path("preview"));
+        assert!(!tree.contains_path("README.md"));
+        assert!(tree.get_root_for_path("ui/other.png") == Some(root.join("mod_b")));
+
+        let mut ignored: Vec<_> = launchpad.ignored_paths().iter().map(|(_, local)| local.clone()).collect();
+        ignored.sort();
+        assert!(ignored == vec![std::path::PathBuf::from("README.md"), "preview".into(), "ui/other.png".into()]);
+
+        // Ignored paths (and the ignore file itself) never reach the collectors
+        let mut collected: Vec<_> = launchpad.collected_in("everything").iter().filter(|(root_path, _)| *root_path == root.join("mod_a")).map(|(_, local)| local.clone()).collect();
+        collected.sort();
+        assert!(collected == vec![std::path::PathBuf::from("ui"), "ui/icon.png".into(), "ui/param.prc".into()]);
+    }
+
+    #[test]
+    fn rule_set_test() {
+        let mut rules = orbit::RuleSet::new();
+        rules.include("**/*.png").unwrap().include("docs/**").unwrap().exclude("ui/keep/**").unwrap();
+        assert!(rules.is_match("ui/preview.png"));
+        assert!(rules.is_match("docs/readme.txt"));
+        assert!(!rules.is_match("ui/keep/icon.png"));
+        assert!(!rules.is_match("ui/preview.PNG"));
+        assert!(!rules.is_match("ui/param.prc"));
+        rules.case_insensitive(true);
+        assert!(rules.is_match("ui/preview.PNG"));
+        assert!(rules.include("ui/[").is_err());
+        assert!(rules.includes().len() == 2);
+        assert!(!orbit::RuleSet::new().is_match("ui/param.prc"));
+        let mut exclude_only = orbit::RuleSet::new();
+        exclude_only.exclude("**/*.bak").unwrap();
+        assert!(!exclude_only.is_match("ui/param.prc") && !exclude_only.is_match("ui/param.bak"));
+
+        let root = test_root("rule_set_test");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_a/ui/preview.png", b"a");
+        write_test_file(&root, "mod_a/info.toml", b"a");
+        let mut collect = orbit::RuleSet::new();
+        collect.include("*.toml").unwrap();
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        launchpad.ignoring_rules(rules);
+        launchpad.collecting_rules(collect);
+        assert!(launchpad.discover_in_root(root.join("mod_a")).unwrap().is_empty());
+        assert!(launchpad.tree().contains_path("ui/param.prc"));
+        assert!(!launchpad.tree().contains_path("ui/preview.png"));
+        assert!(*launchpad.collected_paths() == vec![(root.join("mod_a"), std::path::PathBuf::from("info.toml"))]);
+
+        #[cfg(feature = "serde")]
+        {
+            let mut rules = orbit::RuleSet::new();
+            rules.include("ui/**").unwrap().exclude("**/*.bak").unwrap().case_insensitive(true);
+            let json = serde_json::to_string(&rules).unwrap();
+            let parsed: orbit::RuleSet = serde_json::from_str(&json).unwrap();
+            assert!(parsed == rules && parsed.is_match("ui/param.prc") && !parsed.is_match("ui/param.BAK"));
+            assert!(!serde_json::from_str::<orbit::RuleSet>("{}").unwrap().is_match("ui/param.prc"));
+            assert!(serde_json::from_str::<orbit::RuleSet>("{\"include\": [\"ui/[\"]}").is_err());
+        }
+    }
+
+    #[test]
+    fn collectors_test() {
+        let root = test_root("collectors_test");
+        write_test_file(&root, "mod_a/plugin.nro", b"a");
+        write_test_file(&root, "mod_a/config.json", b"a");
+        write_test_file(&root, "mod_a/ui/preview.png", b"a");
+        write_test_file(&root, "mod_a/ui/param.prc", b"a");
+        write_test_file(&root, "mod_b/plugin.nro", b"b");
+        write_test_file(&root, "mod_b/ui/param.prc", b"b");
+
+        let mut previews = orbit::RuleSet::new();
+        previews.include("**/*.png").unwrap();
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        launchpad.add_collector("plugins", |path| path.extension() == Some("nro".as_ref()), false);
+        launchpad.add_collector("configs", |path| path.extension() == Some("json".as_ref()), false);
+        launchpad.add_collector_rules("previews", previews, true);
+        assert!(launchpad.collector_names() == vec!["plugins", "configs", "previews"]);
+        assert!(launchpad.discover_in_root(root.join("mod_a")).unwrap().is_empty());
+        assert!(launchpad.discover_in_root(root.join("mod_b")).unwrap().len() == 1);
+
+        let plugin = (root.join("mod_a"), std::path::PathBuf::from("plugin.nro"));
+        assert!(launchpad.collected_in("plugins") == [plugin]);
+        assert!(launchpad.collected_in("configs").len() == 1);
+        assert!(launchpad.collected_in("previews").len() == 1);
+        assert!(launchpad.collected_in("missing").is_empty());
+        assert!(launchpad.collected_paths().is_empty());
+        assert!(!launchpad.tree().contains_path("plugin.nro"));
+        assert!(launchpad.tree().contains_path("ui/preview.png"));
+    }
+
+    #[test]
+    fn mount_test() {
+        let root = test_root("mount_test");
+        write_test_file(&root, "flat_a/battlefield/param.prc", b"a");
+        write_test_file(&root, "flat_a/.wh.model.nutexb", b"");
+        write_test_file(&root, "mod_b/stage/battlefield/param.prc", b"b");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::First);
+        assert!(launchpad.discover_in_root_at(root.join("flat_a"), "stage").unwrap().is_empty());
+        let tree = launchpad.tree();
+        assert!(tree.contains_path("stage/battlefield/param.prc"));
+        assert!(!tree.contains_path("battlefield"));
+        assert!(tree.contains_whiteout("stage/model.nutexb"));
+        assert!(tree.load("stage/battlefield/param.prc").unwrap().unwrap() == b"a");
+        assert!(tree.get_full_path("stage/battlefield/param.prc").unwrap() == root.join("flat_a/battlefield/param.prc"));
+        tree.walk_paths(|node, entry_type| {
+            if entry_type == FileEntryType::File {
+                assert!(node.get_mount() == std::path::Path::new("stage"));
+                assert!(node.full_path() == root.join("flat_a/battlefield/param.prc"));
+            }
+        });
+
+        assert!(launchpad.discover_in_root(root.join("mod_b")).unwrap().len() == 1);
+        let mut tree = launchpad.into_tree();
+        assert!(tree.set_active_candidate("stage/battlefield/param.prc", 1));
+        assert!(tree.load("stage/battlefield/param.prc").unwrap().unwrap() == b"b");
+        assert!(tree.get_full_path("stage/battlefield/param.prc").unwrap() == root.join("mod_b/stage/battlefield/param.prc"));
+
+        let mut launchpad = orbit::LaunchPad::from_tree(tree, ConflictHandler::First);
+        match launchpad.discover_in_root_at(root.join("flat_a"), "stage/battlefield/param.prc/inner") {
+            Err(orbit::DiscoveryError::MountConflict { local, .. }) => assert!(local == std::path::Path::new("stage/battlefield/param.prc")),
+            _ => panic!("Mounting underneath a file should fail")
+        }
+        assert!(launchpad.tree().get_entry_type("stage/battlefield/param.prc") == Some(FileEntryType::File));
+    }
+
+    #[test]
+    fn alias_test() {
+        let root = test_root("alias_test");
+        let archive = root.join("data.zip");
+        write_test_zip(&archive, &[("fighter/c00/model.numdlb", b"model")]);
+        write_test_file(&root, "mods/fighter/c00/body.nutexb", b"patched");
+        write_test_file(&root, "user/fighter/c02/body.nutexb", b"user");
+
+        let mut launchpad = orbit::LaunchPad::new(StandardLoader, ConflictHandler::NoRoot);
+        assert!(launchpad.discover_in_root(root.join("mods")).unwrap().is_empty());
+        let mut virt = tree::Tree::new(StandardLoader);
+        virt.insert_file(root.join("user"), "fighter/c02/body.nutexb");
+        let mut orbit = launchpad.launch(ZipLoader::new(&archive), virt);
+
+        assert!(orbit.insert_virtual_alias("fighter/c03/body.nutexb", "fighter/c02/body.nutexb").unwrap().is_none());
+        assert!(orbit.insert_virtual_alias("fighter/c04/body.nutexb", "fighter/c03/body.nutexb").unwrap().is_none());
+        assert!(orbit.load("fighter/c04/body.nutexb").unwrap() == b"user");
+        assert!(orbit.virt().query_filesize("fighter/c04/body.nutexb") == Some(4));
+        assert!(orbit.virt().get_full_path("fighter/c04/body.nutexb").unwrap() == root.join("user/fighter/c02/body.nutexb"));
+        match orbit.insert_virtual_alias("fighter/c02/body.nutexb", "fighter/c04/body.nutexb") {
+            Err(tree::TreeError::AliasCycle(..)) => {},
+            _ => panic!("Aliasing a file to an alias of itself should fail")
+        }
+        assert!(orbit.load("fighter/c02/body.nutexb").unwrap() == b"user");
+
+        assert!(orbit.insert_layer_alias("fighter/c01/body.nutexb", orbit::LayerKind::Patch, "fighter/c00/body.nutexb").is_none());
+        assert!(orbit.insert_layer_alias("fighter/c01/model.numdlb", orbit::LayerKind::Physical, "fighter/c00/model.numdlb").is_none());
+        assert!(orbit.get_layer_alias("fighter/c01/body.nutexb") == Some((orbit::LayerKind::Patch, std::path::Path::new("fighter/c00/body.nutexb"))));
+        assert!(orbit.load("fighter/c01/body.nutexb").unwrap() == b"patched");
+        assert!(orbit.load("fighter/c01/model.numdlb").unwrap() == b"model");
+        assert!(orbit.query_actual_path("fighter/c01/body.nutexb").unwrap() == root.join("mods/fighter/c00/body.nutexb"));
+        assert!(orbit.resolve("fighter/c01/model.numdlb").unwrap().layer == orbit::LayerKind::Physical);
+        assert!(orbit.virt().load("fighter/c01/body.nutexb").unwrap().is_none());
+
+        // Aliased data is never cached, so changes to the target are always picked up
+        orbit.enable_cache(orbit::ContentCache::new(1024));
+        write_test_file(&root, "user/fighter/c05/body.nutexb", b"other");
+        assert!(orbit.load("fighter/c01/body.nutexb").unwrap() == b"patched");
+        assert!(orbit.load("fighter/c03/body.nutexb").unwrap() == b"user");
+        assert!(orbit.cache_stats().unwrap().count == 0);
+        orbit.remove_virtual_path("fighter/c02/body.nutexb");
+        orbit.insert_virtual_file(root.join("user"), "fighter/c05/body.nutexb");
+        assert!(orbit.insert_virtual_alias("fighter/c02/body.nutexb", "fighter/c05/body.nutexb").unwrap().is_none());
+        assert!(orbit.load("fighter/c03/body.nutexb").unwrap() == b"other");
+        assert!(orbit.remove_layer_alias("fighter/c01/body.nutexb").is_some());
+        assert!(orbit.load("fighter/c01/body.nutexb").is_err());
+
+        // A dangling alias is not found, instead of its target being handed to the loader without a root
+        let mut tree = tree::Tree::new(StandardLoader);
+        tree.insert_file(root.join("user/fighter/c02"), "body.nutexb");
+        assert!(tree.insert_alias("alias.nutexb", "body.nutexb").unwrap().is_none());
+        assert!(tree.insert_alias("dangling.toml", "Cargo.toml").unwrap().is_none());
+        assert!(tree.load("dangling.toml").unwrap().is_none());
+        assert!(tree.resolve("dangling.toml").is_none());
+        tree.purify();
+        assert!(tree.contains_path("alias.nutexb"));
+        assert!(tree.load("alias.nutexb").unwrap().unwrap() == b"user");
     }
 }
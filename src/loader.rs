@@ -1,7 +1,37 @@
+use std::convert::TryFrom;
+use std::io::{Cursor, Read, Seek};
 use std::path::{Path, PathBuf};
 
+use thiserror::Error;
+
 use super::FileEntryType;
 
+/// Error type returned by `FileLoader::load_into`
+#[derive(Error, Debug)]
+pub enum LoadIntoError<E> {
+    #[error("File of size {size:#x} does not fit into a buffer of size {capacity:#x}!")]
+    BufferTooSmall {
+        size: usize,
+        capacity: usize
+    },
+    #[error("Loader failed: {0:?}")]
+    Loader(E)
+}
+
+// Copies the part of `data` starting at `offset` into `buf`, returning the number of bytes copied.
+// An offset past the end of the data (including one which doesn't fit into a `usize`) copies nothing
+pub(crate) fn copy_range(data: &[u8], offset: u64, buf: &mut [u8]) -> usize {
+    let start = usize::try_from(offset).map(|offset| offset.min(data.len())).unwrap_or(data.len());
+    let count = buf.len().min(data.len() - start);
+    buf[..count].copy_from_slice(&data[start..start + count]);
+    count
+}
+
+/// Reader type returned by `FileLoader::open`
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
 pub trait FileLoader {
     type ErrorType; // ErrorType to facilitate integration into the user's system
     
@@ -9,6 +39,32 @@ pub trait FileLoader {
     fn get_file_size(&self, root_path: &Path, local_path: &Path) -> Option<usize>;
     fn get_path_type(&self, root_path: &Path, local_path: &Path) -> Result<FileEntryType, Self::ErrorType>;
     fn load_path(&self, root_path: &Path, local_path: &Path) -> Result<Vec<u8>, Self::ErrorType>;
+    /// Opens the path for streaming reads. By default this loads the entire file with `load_path`, so loaders that
+    /// can stream their data should override it.
+    fn open(&self, root_path: &Path, local_path: &Path) -> Result<Box<dyn ReadSeek + '_>, Self::ErrorType> {
+        Ok(Box::new(Cursor::new(self.load_path(root_path, local_path)?)))
+    }
+    /// Reads the file starting at `offset` into `buf`, returning the number of bytes read. Fewer bytes than the length of `buf`
+    /// are only read when the end of the file is reached. By default this loads the entire file with `load_path`.
+    fn read_at(&self, root_path: &Path, local_path: &Path, offset: u64, buf: &mut [u8]) -> Result<usize, Self::ErrorType> {
+        let data = self.load_path(root_path, local_path)?;
+        Ok(copy_range(&data, offset, buf))
+    }
+    /// Loads the file into the start of `buf`, returning the size of the file. If the file does not fit, `LoadIntoError::BufferTooSmall`
+    /// is returned. By default this checks `get_file_size` and then copies the data from `load_path`.
+    fn load_into(&self, root_path: &Path, local_path: &Path, buf: &mut [u8]) -> Result<usize, LoadIntoError<Self::ErrorType>> {
+        if let Some(size) = self.get_file_size(root_path, local_path) {
+            if size > buf.len() {
+                return Err(LoadIntoError::BufferTooSmall { size, capacity: buf.len() });
+            }
+        }
+        let data = self.load_path(root_path, local_path).map_err(LoadIntoError::Loader)?;
+        if data.len() > buf.len() {
+            return Err(LoadIntoError::BufferTooSmall { size: data.len(), capacity: buf.len() });
+        }
+        buf[..data.len()].copy_from_slice(&data);
+        Ok(data.len())
+    }
     fn get_actual_path(&self, root_path: &Path, local_path: &Path) -> Option<PathBuf> {
         Some(root_path.join(local_path))
     }
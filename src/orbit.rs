@@ -1,156 +1,689 @@
+use std::borrow::Cow;
 use std::fmt::Debug;
-use std::path::{Path, PathBuf};
-use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::sync::Arc;
 
 use crate::{FileEntryType, ConflictHandler};
-use crate::loader::FileLoader;
-use crate::tree::{Tree, node::Node};
+use crate::loader::{copy_range, FileLoader, LoadIntoError, ReadSeek};
+use crate::tree::{Candidate, Subtree, Tree, TreeError, node::Node};
 
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use thiserror::Error;
 use walkdir::WalkDir;
 
+pub mod layered;
+pub mod cache;
+pub mod resolver;
+pub mod choices;
+pub mod report;
+pub mod matrix;
+pub mod rules;
+
+pub use layered::{Layer, LayerError, LayeredOrbit};
+pub use cache::{CacheStats, ContentCache};
+pub use resolver::{ConflictAction, ConflictCandidate, ConflictResolver};
+pub use choices::{ChoicesError, ConflictChoices};
+pub use matrix::ConflictMatrix;
+pub use rules::{RuleError, RuleSet};
+pub use report::{ConflictReport, ConflictSummary, ReportEntry, ReportKind, RootPairConflicts};
+
 pub struct LaunchPad<A: FileLoader> {
     tree: Tree<A>,
-    handler: ConflictHandler,
+    resolver: Box<dyn ConflictResolver>,
     ignore: Box<dyn Fn(&Path) -> bool + Send>,
     collect: Box<dyn Fn(&Path) -> bool + Send>,
-    collected: Vec<(PathBuf, PathBuf)>
+    collected: Vec<(PathBuf, PathBuf)>,
+    collectors: Vec<Collector>,
+    global_ignore: Gitignore,
+    report_ignored: bool,
+    ignored: Vec<(PathBuf, PathBuf)>,
+    priorities: HashMap<PathBuf, i32>,
+    accept_duplicates: bool,
+    choices: ConflictChoices,
+    conflicts: Vec<ConflictKind>
 }
 
+/// A conflict found during discovery
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ConflictKind {
+    /// A file conflict where the file from `source_root` is served instead of the file from `error_root`
     StandardConflict {
         error_root: PathBuf,
         source_root: PathBuf,
         local: PathBuf
     },
-    RootConflict(PathBuf, PathBuf)
+    /// A file and a directory at the same local path. The entry from `source_root` is served, and `error_type` is the type of the entry from `error_root`.
+    /// A directory's root is the first root which provided it (or an entry underneath it) that is still in the tree, see `Tree::directory_roots`
+    TypeConflict {
+        error_root: PathBuf,
+        source_root: PathBuf,
+        local: PathBuf,
+        error_type: FileEntryType
+    },
+    /// `root` was rejected because its file at `local` conflicted with the file from `existing_root`
+    RootConflict {
+        root: PathBuf,
+        existing_root: PathBuf,
+        local: PathBuf
+    },
+    /// A file which is byte-identical to the file that is already in the tree, see `LaunchPad::accepting_duplicates`.
+    /// The existing file is kept
+    Duplicate {
+        root: PathBuf,
+        existing_root: PathBuf,
+        local: PathBuf
+    }
 }
 
+/// Errors which stop the discovery of a root
+#[derive(Error, Debug)]
+pub enum DiscoveryError {
+    /// A file conflict was found with `ConflictHandler::Strict`. Both paths are full paths
+    #[error("File conflict: path '{path}' found when path '{existing}' is already included!")]
+    Conflict {
+        path: PathBuf,
+        existing: PathBuf
+    },
+    #[error("Path '{path}' found in root '{root}' is not physically in root! Possible symlink?")]
+    OutsideRoot {
+        root: PathBuf,
+        path: PathBuf
+    },
+    #[error("Entry '{0}' found without finding it first!")]
+    PhantomEntry(PathBuf),
+    #[error("Cannot mount root '{root}' at '{mount}' because '{local}' is not a directory!")]
+    MountConflict {
+        root: PathBuf,
+        mount: PathBuf,
+        local: PathBuf
+    },
+    #[error("Failed to read ignore file '{path}'! Error: {error}")]
+    IgnoreFile {
+        path: PathBuf,
+        #[source]
+        error: ignore::Error
+    }
+}
+
+// Changes made to the tree while discovering a root, so that a rejected root can be rolled back
+enum JournalEntry {
+    Created(PathBuf),
+    Modified {
+        local: PathBuf,
+        candidates: Vec<Candidate>,
+        active: usize
+    },
+    Removed(Box<Subtree>),
+    Opaque {
+        local: PathBuf,
+        root: PathBuf
+    },
+    // A path was pushed onto the default collector (`None`), or onto one of the named collectors
+    Collected(Option<usize>),
+    Ignored
+}
+
+// A named category of collected paths, see `LaunchPad::add_collector`
+struct Collector {
+    name: String,
+    matcher: Box<dyn Fn(&Path) -> bool + Send>,
+    insert: bool,
+    paths: Vec<(PathBuf, PathBuf)>
+}
+
+enum RootOutcome {
+    Accepted(Vec<ConflictKind>),
+    Rejected(ConflictKind)
+}
+
+/// A file with this name at the top of a root lists paths (in gitignore syntax) that discovery should ignore in that root
+pub const IGNORE_FILE: &str = ".orbitsignore";
+
+/// A file with this name marks the directory it is in as opaque (see `Tree::add_opaque_root`), hiding everything underneath it in the layers below the patch layer
+/// and whatever other roots put underneath it in the patch layer
+pub const OPAQUE_MARKER: &str = ".wh..wh..opq";
+
+/// Files in a root whose name starts with this prefix are whiteout markers. `.wh.param.prc` hides `param.prc` from the layers below the patch layer.
+pub const WHITEOUT_PREFIX: &str = ".wh.";
+
 fn default_conditional(_: &Path) -> bool { false }
 
-impl<A: FileLoader> LaunchPad<A> where <A as FileLoader>::ErrorType: Debug {
-    fn handle_conflict(&mut self, root_path: &Path, local_path: &Path) -> Option<ConflictKind> {
-        match self.handler {
-            ConflictHandler::Strict => {
-                if let Some(root) = self.tree.get_root_for_path(local_path) {
-                    panic!("File conflict: path '{}' found when path '{}' is already included!", root_path.join(local_path).display(), root.join(local_path).display());
-                } else {
-                    panic!("File conflict: path '{}' found when path '{}' is already included!", root_path.join(local_path).display(), local_path.display());
-                }
-            },
-            ConflictHandler::NoRoot => {
-                let mut removed_files = self.tree.remove_paths_by_root(root_path);
-                removed_files.push(root_path.join(local_path));
-                if let Some(root) = self.tree.get_root_for_path(local_path) {
-                    Some(ConflictKind::RootConflict(root_path.to_path_buf(), root.join(local_path)))
-                } else {
-                    Some(ConflictKind::RootConflict(root_path.to_path_buf(), local_path.to_path_buf()))
-                }
+fn whiteout_target(local_path: &Path) -> Option<PathBuf> {
+    let name = local_path.file_name()?.to_str()?;
+    match name.strip_prefix(WHITEOUT_PREFIX) {
+        Some(target) if !target.is_empty() => Some(local_path.with_file_name(target)),
+        _ => None
+    }
+}
+
+// `Orbit` normalizes local paths before looking them up in the trees, the transformers and the `ContentCache`,
+// so that `ui/param.prc`, `ui/./param.prc` and `ui/msg/../param.prc` are the same file everywhere
+fn normalize(path: &Path) -> Cow<'_, Path> {
+    if !path.components().any(|component| matches!(component, Component::CurDir | Component::ParentDir)) {
+        return Cow::Borrowed(path);
+    }
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                normalized.pop();
             },
-            ConflictHandler::First =>  {
-                if let Some(root) = self.tree.get_root_for_path(local_path) {
-                    Some(ConflictKind::StandardConflict {
-                        error_root: root_path.to_path_buf(),
-                        source_root: root,
-                        local: local_path.to_path_buf()
-                    })
-                } else {
-                    Some(ConflictKind::StandardConflict {
-                        error_root: root_path.to_path_buf(),
-                        source_root: PathBuf::new(),
-                        local: local_path.to_path_buf()
-                    })
-                }
+            component => normalized.push(component)
+        }
+    }
+    Cow::Owned(normalized)
+}
+
+impl<A: FileLoader> LaunchPad<A> where <A as FileLoader>::ErrorType: Debug {
+    // Decides what to do about an entry which collides with an existing entry, returning the action along with the conflict to report
+    fn handle_conflict(&mut self, root_path: &Path, mount_path: &Path, local_path: &Path, whiteout: bool, entry_type: FileEntryType) -> Result<(ConflictAction, ConflictKind), DiscoveryError> {
+        let existing_whiteout = self.tree.contains_whiteout(local_path);
+        let existing_type = self.tree.get_entry_type(local_path).unwrap_or(FileEntryType::File);
+        let existing_root = match existing_type {
+            FileEntryType::Directory => self.tree.directory_roots(local_path).first().cloned().unwrap_or_default(),
+            FileEntryType::File => self.tree.get_root_for_path(local_path).unwrap_or_default()
+        };
+        // Whiteouts hide whatever is below them, so only entries which aren't whiteouts can mismatch
+        let mismatch = existing_type != entry_type && !whiteout && !(existing_whiteout && entry_type == FileEntryType::File);
+        if self.accept_duplicates && !mismatch && !whiteout && !existing_whiteout && self.is_duplicate(root_path, mount_path, local_path) {
+            return Ok((ConflictAction::KeepExisting, ConflictKind::Duplicate {
+                root: root_path.to_path_buf(),
+                existing_root,
+                local: local_path.to_path_buf()
+            }));
+        }
+        let existing = ConflictCandidate {
+            root: &existing_root,
+            local: local_path,
+            priority: self.root_priority(&existing_root),
+            whiteout: existing_whiteout,
+            entry_type: existing_type
+        };
+        let incoming = ConflictCandidate {
+            root: root_path,
+            local: local_path,
+            priority: self.root_priority(root_path),
+            whiteout,
+            entry_type
+        };
+        let action = match self.choices.get(local_path) {
+            Some(chosen) if chosen == root_path => ConflictAction::Replace,
+            Some(chosen) if chosen == existing_root => ConflictAction::KeepExisting,
+            _ => self.resolver.resolve(&existing, &incoming)
+        };
+        let (error_root, source_root, error_type) = match action {
+            ConflictAction::KeepExisting => (root_path.to_path_buf(), existing_root, entry_type),
+            ConflictAction::Replace => (existing_root, root_path.to_path_buf(), existing_type),
+            ConflictAction::RejectRoot => {
+                return Ok((action, ConflictKind::RootConflict {
+                    root: root_path.to_path_buf(),
+                    existing_root,
+                    local: local_path.to_path_buf()
+                }));
             },
-            ConflictHandler::Last => None
+            ConflictAction::Error => {
+                let existing = match (existing_type, self.tree.resolve(local_path)) {
+                    (FileEntryType::File, Some((root, local))) => root.join(local),
+                    _ => existing_root.join(local_path)
+                };
+                return Err(DiscoveryError::Conflict {
+                    path: root_path.join(local_path.strip_prefix(mount_path).unwrap_or(local_path)),
+                    existing
+                });
+            }
+        };
+        let local = local_path.to_path_buf();
+        if mismatch {
+            Ok((action, ConflictKind::TypeConflict { error_root, source_root, local, error_type }))
+        } else {
+            Ok((action, ConflictKind::StandardConflict { error_root, source_root, local }))
         }
     }
 
-    pub fn new(loader: A, handler: ConflictHandler) -> Self {
-        Self {
-            tree: Tree::new(loader),
-            handler,
-            ignore: Box::new(default_conditional),
-            collect: Box::new(default_conditional),
-            collected: Vec::new()
+    // Compares the sizes and then the contents of both files. Files which can't be loaded are never duplicates
+    fn is_duplicate(&self, root_path: &Path, mount_path: &Path, local_path: &Path) -> bool {
+        let (existing_root, existing_local) = match self.tree.resolve(local_path) {
+            Some(resolved) => resolved,
+            None => return false
+        };
+        let source_local = local_path.strip_prefix(mount_path).unwrap_or(local_path);
+        let loader = self.tree.loader();
+        if existing_root.as_os_str().is_empty() || !matches!(loader.get_path_type(&existing_root, &existing_local), Ok(FileEntryType::File)) {
+            return false;
+        }
+        match (loader.get_file_size(&existing_root, &existing_local), loader.get_file_size(root_path, source_local)) {
+            (Some(existing), Some(size)) if existing == size => {},
+            _ => return false
         }
+        match (loader.load_path(&existing_root, &existing_local), loader.load_path(root_path, source_local)) {
+            (Ok(existing), Ok(data)) => existing == data,
+            _ => false
+        }
+    }
+
+    fn insert_entry(&mut self, root_path: &Path, mount_path: &Path, local_path: &Path, whiteout: bool) -> Option<(PathBuf, PathBuf)> {
+        let inserted = if whiteout {
+            self.tree.insert_mounted_whiteout(root_path, mount_path, local_path)
+        } else {
+            self.tree.insert_mounted_file(root_path, mount_path, local_path)
+        };
+        inserted.expect("Discovered paths are always inside of the mount path")
+    }
+
+    pub fn new(loader: A, handler: ConflictHandler) -> Self {
+        Self::from_tree(Tree::new(loader), handler)
     }
 
     pub fn from_tree(tree: Tree<A>, handler: ConflictHandler) -> Self {
+        Self::from_tree_with_resolver(tree, handler)
+    }
+
+    /// Creates a `LaunchPad` which uses a custom `ConflictResolver` instead of a `ConflictHandler`
+    pub fn with_resolver<R: ConflictResolver + 'static>(loader: A, resolver: R) -> Self {
+        Self::from_tree_with_resolver(Tree::new(loader), resolver)
+    }
+
+    pub fn from_tree_with_resolver<R: ConflictResolver + 'static>(tree: Tree<A>, resolver: R) -> Self {
         Self {
             tree,
-            handler,
+            resolver: Box::new(resolver),
             ignore: Box::new(default_conditional),
             collect: Box::new(default_conditional),
-            collected: Vec::new()
+            collected: Vec::new(),
+            collectors: Vec::new(),
+            global_ignore: Gitignore::empty(),
+            report_ignored: false,
+            ignored: Vec::new(),
+            priorities: HashMap::new(),
+            accept_duplicates: false,
+            choices: ConflictChoices::new(),
+            conflicts: Vec::new()
         }
     }
 
-    pub fn discover_in_root<P: AsRef<Path>>(&mut self, root: P) -> Vec<ConflictKind> {
-        let root = root.as_ref();
+    /// Gets every conflict returned by discovery so far
+    pub fn conflicts(&self) -> &[ConflictKind] {
+        &self.conflicts
+    }
+
+    /// Builds a `ConflictReport` out of every conflict returned by discovery so far
+    pub fn conflict_report(&self) -> ConflictReport {
+        ConflictReport::from_conflicts(&self.conflicts)
+    }
+
+    /// Builds a `ConflictMatrix` out of every conflict returned by discovery so far
+    pub fn conflict_matrix(&self) -> ConflictMatrix {
+        self.conflict_report().matrix()
+    }
+
+    /// Sets whether or not files which are byte-identical to the file already in the tree are accepted.
+    /// Accepted duplicates are reported as `ConflictKind::Duplicate` instead of being passed to the resolver
+    pub fn accepting_duplicates(&mut self, accept: bool) {
+        self.accept_duplicates = accept;
+    }
+
+    /// Sets the user's conflict choices. A conflict between the chosen root and another root is always won by the chosen root,
+    /// any other conflict falls back to the resolver
+    pub fn set_choices(&mut self, choices: ConflictChoices) {
+        self.choices = choices;
+    }
+
+    pub fn choices(&self) -> &ConflictChoices {
+        &self.choices
+    }
+
+    pub fn choices_mut(&mut self) -> &mut ConflictChoices {
+        &mut self.choices
+    }
+
+    /// Gets every `(local, root)` choice whose root no longer exists, see `ConflictChoices::stale`
+    pub fn stale_choices(&self) -> Vec<(PathBuf, PathBuf)> {
+        self.choices.stale()
+    }
+
+    /// Sets the resolver used for file conflicts in every root discovered from now on
+    pub fn set_resolver<R: ConflictResolver + 'static>(&mut self, resolver: R) {
+        self.resolver = Box::new(resolver);
+    }
+
+    // Inserts a directory along with any missing parents, recording the topmost entry that was created so it can be rolled back
+    fn insert_directory_logged(&mut self, root_path: &Path, local_path: &Path, journal: &mut Vec<JournalEntry>) -> Option<(PathBuf, PathBuf)> {
+        if let Some(created) = self.first_missing_path(local_path) {
+            journal.push(JournalEntry::Created(created));
+        }
+        self.tree.insert_directory(root_path, local_path)
+    }
+
+    fn insert_entry_logged(&mut self, root_path: &Path, mount_path: &Path, local_path: &Path, whiteout: bool, journal: &mut Vec<JournalEntry>) -> Option<(PathBuf, PathBuf)> {
+        if let Some(created) = self.first_missing_path(local_path) {
+            journal.push(JournalEntry::Created(created));
+        }
+        self.insert_entry(root_path, mount_path, local_path, whiteout)
+    }
+
+    // Detaches an existing entry, and everything underneath it, so that it can be restored during a rollback
+    fn take_logged(&mut self, local_path: &Path, journal: &mut Vec<JournalEntry>) {
+        if let Some(subtree) = self.tree.take_path(local_path) {
+            journal.push(JournalEntry::Removed(Box::new(subtree)));
+        }
+    }
+
+    // Adds the root as a candidate for an existing file, keeping the candidates ordered by root priority and then by discovery order.
+    // Returns false if there is no file to add the candidate to
+    fn add_candidate_logged(&mut self, root_path: &Path, mount_path: &Path, local_path: &Path, whiteout: bool, activate: bool, journal: &mut Vec<JournalEntry>) -> bool {
+        let active = match self.tree.active_candidate(local_path) {
+            Some(active) => active,
+            None => return false
+        };
+        let previous = self.tree.candidates(local_path).to_vec();
+        let mut candidates = previous.clone();
+        let mut new_active = active;
+        let index = match candidates.iter().position(|candidate| candidate.root == root_path) {
+            Some(index) => {
+                candidates[index].whiteout = whiteout;
+                candidates[index].mount = mount_path.to_path_buf();
+                index
+            },
+            None => {
+                let priority = self.root_priority(root_path);
+                let index = candidates
+                    .iter()
+                    .position(|candidate| self.root_priority(&candidate.root) < priority)
+                    .unwrap_or(candidates.len());
+                candidates.insert(index, Candidate {
+                    root: root_path.to_path_buf(),
+                    whiteout,
+                    mount: mount_path.to_path_buf()
+                });
+                if index <= active {
+                    new_active += 1;
+                }
+                index
+            }
+        };
+        if activate {
+            new_active = index;
+        }
+        self.tree.set_candidates(local_path, candidates, new_active);
+        journal.push(JournalEntry::Modified {
+            local: local_path.to_path_buf(),
+            candidates: previous,
+            active
+        });
+        true
+    }
+
+    // Replaces an existing entry with a file. Existing files keep the replaced root as a candidate, while anything else is detached
+    fn replace_entry_logged(&mut self, root_path: &Path, mount_path: &Path, local_path: &Path, whiteout: bool, journal: &mut Vec<JournalEntry>) {
+        if !self.add_candidate_logged(root_path, mount_path, local_path, whiteout, true, journal) {
+            self.take_logged(local_path, journal);
+            self.insert_entry_logged(root_path, mount_path, local_path, whiteout, journal);
+        }
+    }
+
+    fn first_missing_path(&self, local_path: &Path) -> Option<PathBuf> {
+        let mut ancestors: Vec<&Path> = local_path.ancestors().filter(|path| *path != Path::new("")).collect();
+        ancestors.reverse();
+        ancestors
+            .into_iter()
+            .find(|path| !self.tree.contains_entry(path) && !self.tree.contains_whiteout(path))
+            .map(Path::to_path_buf)
+    }
+
+    fn root_ignore(root: &Path) -> Result<Option<Gitignore>, DiscoveryError> {
+        let path = root.join(IGNORE_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let mut builder = GitignoreBuilder::new(root);
+        if let Some(error) = builder.add(&path) {
+            return Err(DiscoveryError::IgnoreFile { path, error });
+        }
+        match builder.build() {
+            Ok(ignore) => Ok(Some(ignore)),
+            Err(error) => Err(DiscoveryError::IgnoreFile { path, error })
+        }
+    }
+
+    fn is_glob_ignored(&self, root_ignore: Option<&Gitignore>, local_path: &Path, is_dir: bool) -> bool {
+        self.global_ignore.matched(local_path, is_dir).is_ignore()
+            || root_ignore.map(|ignore| ignore.matched(local_path, is_dir).is_ignore()).unwrap_or(false)
+    }
+
+    // Creates the directories that a root is mounted at. Anything other than a directory in the way of the mount path is an error
+    fn insert_mount_logged(&mut self, root: &Path, mount: &Path, journal: &mut Vec<JournalEntry>) -> Result<(), DiscoveryError> {
+        for path in mount.ancestors().filter(|path| *path != Path::new("")) {
+            match self.tree.get_entry_type(path) {
+                Some(FileEntryType::Directory) => {},
+                None if !self.tree.contains_whiteout(path) => {},
+                _ => return Err(DiscoveryError::MountConflict {
+                    root: root.to_path_buf(),
+                    mount: mount.to_path_buf(),
+                    local: path.to_path_buf()
+                })
+            }
+        }
+        if !mount.as_os_str().is_empty() {
+            self.insert_directory_logged(Path::new(""), mount, journal);
+        }
+        Ok(())
+    }
+
+    fn discover_entries(&mut self, root: &Path, mount: &Path, journal: &mut Vec<JournalEntry>) -> Result<RootOutcome, DiscoveryError> {
+        let root_ignore = Self::root_ignore(root)?;
+        self.insert_mount_logged(root, mount, journal)?;
         let mut conflicts = Vec::new();
-        for entry in WalkDir::new(root)
+        let mut walker = WalkDir::new(root)
             .min_depth(1)
-            .into_iter() {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                let local_path = path.strip_prefix(root).expect("Path found in root is not physically in root! Possible symlink?");
-                let local_pathbuf = local_path.to_path_buf();
-                if (*self.collect)(&local_pathbuf) {
-                    self.collected.push((root.to_path_buf(), local_pathbuf));
+            .into_iter();
+        while let Some(entry) = walker.next() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => continue
+            };
+            let path = entry.path();
+            let local_path = match path.strip_prefix(root) {
+                Ok(local_path) => local_path,
+                Err(_) => return Err(DiscoveryError::OutsideRoot {
+                    root: root.to_path_buf(),
+                    path: path.to_path_buf()
+                })
+            };
+            let is_dir = entry.file_type().is_dir();
+            if local_path == Path::new(IGNORE_FILE) {
+                continue;
+            }
+            // Ignored entries are never collected, and nothing underneath an ignored directory is visited
+            if self.is_glob_ignored(root_ignore.as_ref(), local_path, is_dir) {
+                if self.report_ignored {
+                    self.ignored.push((root.to_path_buf(), local_path.to_path_buf()));
+                    journal.push(JournalEntry::Ignored);
+                }
+                if is_dir {
+                    walker.skip_current_dir();
+                }
+                continue;
+            }
+            if (*self.collect)(local_path) {
+                self.collected.push((root.to_path_buf(), local_path.to_path_buf()));
+                journal.push(JournalEntry::Collected(None));
+                continue;
+            }
+            if let Some(index) = self.collectors.iter().position(|collector| (*collector.matcher)(local_path)) {
+                let collector = &mut self.collectors[index];
+                collector.paths.push((root.to_path_buf(), local_path.to_path_buf()));
+                journal.push(JournalEntry::Collected(Some(index)));
+                if !collector.insert {
                     continue;
                 }
-                if (*self.ignore)(&local_pathbuf) {
+            }
+            if (*self.ignore)(local_path) {
+                continue;
+            }
+            if entry.file_type().is_file() && local_path.file_name() == Some(OPAQUE_MARKER.as_ref()) {
+                let directory = mount.join(local_path.parent().unwrap_or_else(|| Path::new("")));
+                if !self.tree.opaque_roots(&directory).iter().any(|owner| owner == root) && self.tree.add_opaque_root(&directory, root) {
+                    journal.push(JournalEntry::Opaque {
+                        local: directory,
+                        root: root.to_path_buf()
+                    });
+                }
+                continue;
+            }
+            // Everything above works with the path inside of the root, while the tree and conflicts work with the mounted path
+            let (local_path, whiteout, entry_type) = if entry.file_type().is_dir() {
+                let local_path = mount.join(local_path);
+                if self.tree.get_entry_type(&local_path) == Some(FileEntryType::Directory) {
                     continue;
                 }
-                drop(local_pathbuf);
-                if entry.file_type().is_dir() {
-                    if !self.tree.contains_path(local_path) {
-                        self.tree.insert_directory(root, local_path);
-                    }
-                } else if entry.file_type().is_file() {
-                    if self.tree.contains_path(local_path) {
-                        if let Some(conflict) = self.handle_conflict(root, local_path) {
-                            match conflict {
-                                ConflictKind::RootConflict(bad_root, conflict_file) => {
-                                    return vec![ConflictKind::RootConflict(bad_root, conflict_file)];
-                                },
-                                conflict => {
-                                    conflicts.push(conflict);
-                                }
-                            }
-                        } else if let Some((error_root, local)) = self.tree.insert_file(root, local_path) {
-                            conflicts.push(ConflictKind::StandardConflict {
-                                error_root,
-                                source_root: root.to_path_buf(),
-                                local
-                            });
-                        }
-                    } else {
-                        if self.tree.insert_file(root, local_path).is_some() {
-                            panic!("Entry found without finding it first!");
+                (local_path, false, FileEntryType::Directory)
+            } else if entry.file_type().is_file() {
+                match whiteout_target(local_path) {
+                    Some(target) => (mount.join(target), true, FileEntryType::File),
+                    None => (mount.join(local_path), false, FileEntryType::File)
+                }
+            } else {
+                continue;
+            };
+            let local_path = local_path.as_path();
+            if !self.tree.contains_entry(local_path) && !self.tree.contains_whiteout(local_path) {
+                let inserted = match entry_type {
+                    FileEntryType::Directory => self.insert_directory_logged(root, local_path, journal),
+                    FileEntryType::File => self.insert_entry_logged(root, mount, local_path, whiteout, journal)
+                };
+                match inserted {
+                    None => {},
+                    // A whiteout which can fall back to a file is in the way (see `Tree::insert_whiteout`), so nothing underneath it can be added
+                    Some((existing_root, blocked)) if blocked != local_path => {
+                        if entry_type == FileEntryType::Directory {
+                            walker.skip_current_dir();
                         }
-                    }
+                        conflicts.push(ConflictKind::TypeConflict {
+                            error_root: root.to_path_buf(),
+                            source_root: existing_root,
+                            local: blocked,
+                            error_type: FileEntryType::Directory
+                        });
+                    },
+                    Some(_) => return Err(DiscoveryError::PhantomEntry(root.join(local_path.strip_prefix(mount).unwrap_or(local_path))))
+                }
+                continue;
+            }
+            let (action, conflict) = self.handle_conflict(root, mount, local_path, whiteout, entry_type)?;
+            match (action, entry_type) {
+                (ConflictAction::RejectRoot, _) => return Ok(RootOutcome::Rejected(conflict)),
+                (ConflictAction::KeepExisting, FileEntryType::File) => {
+                    self.add_candidate_logged(root, mount, local_path, whiteout, false, journal);
+                },
+                // The existing entry is kept, so nothing from this directory can be added
+                (ConflictAction::KeepExisting, FileEntryType::Directory) => walker.skip_current_dir(),
+                (ConflictAction::Replace, FileEntryType::File) => self.replace_entry_logged(root, mount, local_path, whiteout, journal),
+                (ConflictAction::Replace, FileEntryType::Directory) => {
+                    self.take_logged(local_path, journal);
+                    self.insert_directory_logged(root, local_path, journal);
+                },
+                (ConflictAction::Error, _) => unreachable!("Conflict errors are returned by handle_conflict")
+            }
+            conflicts.push(conflict);
+        }
+        Ok(RootOutcome::Accepted(conflicts))
+    }
+
+    // Removes everything a rejected root added to the tree and to the collected (and ignored) paths
+    fn rollback_root(&mut self, root: &Path, journal: Vec<JournalEntry>) {
+        for entry in journal.into_iter().rev() {
+            match entry {
+                JournalEntry::Created(local) => {
+                    let _ = self.tree.remove_path(local);
+                },
+                JournalEntry::Modified { local, candidates, active } => {
+                    self.tree.set_candidates(local, candidates, active);
+                },
+                JournalEntry::Removed(subtree) => self.tree.restore_path(*subtree),
+                JournalEntry::Opaque { local, root } => {
+                    self.tree.remove_opaque_root(local, root);
+                },
+                JournalEntry::Collected(None) => {
+                    self.collected.pop();
+                },
+                JournalEntry::Collected(Some(index)) => {
+                    self.collectors[index].paths.pop();
+                },
+                JournalEntry::Ignored => {
+                    self.ignored.pop();
                 }
             }
         }
-        conflicts
+        self.tree.forget_root(root);
     }
 
-    pub fn discover_roots<P: AsRef<Path>, F: Fn(&Path) -> bool>(&mut self, path: P, depth: usize, filter: F) -> Vec<ConflictKind> {
+    /// Discovers every entry in the root and adds it to the tree, returning the conflicts that were found.
+    /// Discovery of a root is transactional: if the root is rejected (see `ConflictAction::RejectRoot`) or an error is returned,
+    /// none of its files, directories, or collected paths are kept and any files it replaced are restored.
+    /// A rejected root is reported as a single `ConflictKind::RootConflict`.
+    /// An error is returned if the resolver returns `ConflictAction::Error`, or if the root could not be walked correctly
+    pub fn discover_in_root<P: AsRef<Path>>(&mut self, root: P) -> Result<Vec<ConflictKind>, DiscoveryError> {
+        self.discover_in_root_at(root, "")
+    }
+
+    /// Discovers every entry in the root and adds it to the tree underneath `mount_prefix`, so that `<root>/stage/param.prc` mounted at `assets`
+    /// becomes `assets/stage/param.prc`. The loader (and `Node::full_path`) still use the path inside of the root.
+    /// Ignore files, ignore rules and collectors match paths inside of the root, while conflicts and choices use the mounted path.
+    /// This behaves like `discover_in_root` otherwise, and returns `DiscoveryError::MountConflict` if a file is in the way of the mount prefix
+    pub fn discover_in_root_at<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, root: P, mount_prefix: Q) -> Result<Vec<ConflictKind>, DiscoveryError> {
+        let root = root.as_ref();
+        let mut journal = Vec::new();
+        match self.discover_entries(root, mount_prefix.as_ref(), &mut journal) {
+            Ok(RootOutcome::Accepted(conflicts)) => {
+                self.conflicts.extend(conflicts.iter().cloned());
+                Ok(conflicts)
+            },
+            Ok(RootOutcome::Rejected(conflict)) => {
+                self.rollback_root(root, journal);
+                self.conflicts.push(conflict.clone());
+                Ok(vec![conflict])
+            },
+            Err(e) => {
+                self.rollback_root(root, journal);
+                Err(e)
+            }
+        }
+    }
+
+    /// Sets the priority of `root`, which is used by `ConflictHandler::Priority` and passed to custom resolvers. Roots without a priority default to 0
+    pub fn set_root_priority<P: AsRef<Path>>(&mut self, root: P, priority: i32) {
+        self.priorities.insert(root.as_ref().to_path_buf(), priority);
+    }
+
+    pub fn root_priority<P: AsRef<Path>>(&self, root: P) -> i32 {
+        self.priorities.get(root.as_ref()).copied().unwrap_or(0)
+    }
+
+    /// Sets the priority of `root` and then discovers it, see `discover_in_root`
+    pub fn discover_in_root_with_priority<P: AsRef<Path>>(&mut self, root: P, priority: i32) -> Result<Vec<ConflictKind>, DiscoveryError> {
+        self.set_root_priority(root.as_ref(), priority);
+        self.discover_in_root(root)
+    }
+
+    /// Discovers every root at `depth` inside of `path` which passes the filter. Discovery stops at the first error
+    pub fn discover_roots<P: AsRef<Path>, F: Fn(&Path) -> bool>(&mut self, path: P, depth: usize, filter: F) -> Result<Vec<ConflictKind>, DiscoveryError> {
         let path = path.as_ref();
         let mut conflicts = Vec::new();
         for entry in WalkDir::new(path)
             .min_depth(depth)
             .max_depth(depth)
-            .into_iter() {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if filter(&path) {
-                    conflicts.append(&mut self.discover_in_root(path));
-                }
+            .into_iter()
+            .flatten() {
+            let path = entry.path();
+            if filter(path) {
+                conflicts.append(&mut self.discover_in_root(path)?);
             }
         }
-        conflicts
+        Ok(conflicts)
     }
 
     pub fn into_tree(self) -> Tree<A> {
@@ -166,11 +699,81 @@ impl<A: FileLoader> LaunchPad<A> where <A as FileLoader>::ErrorType: Debug {
         self.collect = Box::new(collect_fn);
     }
 
-    pub fn collected_paths<'a>(&'a self) -> &'a Vec<(PathBuf, PathBuf)> {
+    /// Ignores every path which matches the rule set, replacing the closure given to `LaunchPad::ignoring`
+    pub fn ignoring_rules(&mut self, rules: RuleSet) {
+        self.ignoring(move |path| rules.is_match(path));
+    }
+
+    /// Collects every path which matches the rule set, replacing the closure given to `LaunchPad::collecting`
+    pub fn collecting_rules(&mut self, rules: RuleSet) {
+        self.collecting(move |path| rules.is_match(path));
+    }
+
+    pub fn collected_paths(&self) -> &Vec<(PathBuf, PathBuf)> {
         &self.collected
     }
 
-    pub fn tree<'a>(&'a self) -> &'a Tree<A> {
+    /// Registers a named collector. Paths which match `collect_fn` are collected into the category instead of being added to the tree,
+    /// unless `insert_into_tree` is set, in which case they are added to the tree as well.
+    /// Collectors are checked after the closure given to `LaunchPad::collecting`, in the order they were added, and a path goes to the first collector that matches it.
+    /// Ignored paths (see `LaunchPad::ignoring_globs`) are never collected.
+    /// Adding a collector with the name of an existing collector replaces its matcher but keeps its paths
+    pub fn add_collector<S: Into<String>, F: Fn(&Path) -> bool + Send + 'static>(&mut self, name: S, collect_fn: F, insert_into_tree: bool) {
+        let name = name.into();
+        match self.collectors.iter_mut().find(|collector| collector.name == name) {
+            Some(collector) => {
+                collector.matcher = Box::new(collect_fn);
+                collector.insert = insert_into_tree;
+            },
+            None => self.collectors.push(Collector {
+                name,
+                matcher: Box::new(collect_fn),
+                insert: insert_into_tree,
+                paths: Vec::new()
+            })
+        }
+    }
+
+    /// Registers a named collector which collects every path matching the rule set, see `LaunchPad::add_collector`
+    pub fn add_collector_rules<S: Into<String>>(&mut self, name: S, rules: RuleSet, insert_into_tree: bool) {
+        self.add_collector(name, move |path| rules.is_match(path), insert_into_tree);
+    }
+
+    /// Gets the `(root, local)` paths collected by the named collector. Unknown collectors have no paths
+    pub fn collected_in<S: AsRef<str>>(&self, name: S) -> &[(PathBuf, PathBuf)] {
+        self.collectors
+            .iter()
+            .find(|collector| collector.name == name.as_ref())
+            .map(|collector| collector.paths.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn collector_names(&self) -> Vec<&str> {
+        self.collectors.iter().map(|collector| collector.name.as_str()).collect()
+    }
+
+    /// Ignores every path which matches one of the globs (in gitignore syntax) in every root, on top of each root's own `IGNORE_FILE`
+    pub fn ignoring_globs<S: AsRef<str>>(&mut self, globs: &[S]) -> Result<(), ignore::Error> {
+        let mut builder = GitignoreBuilder::new("");
+        for glob in globs.iter() {
+            builder.add_line(None, glob.as_ref())?;
+        }
+        self.global_ignore = builder.build()?;
+        Ok(())
+    }
+
+    /// Sets whether or not the paths ignored by globs or ignore files are recorded, see `LaunchPad::ignored_paths`
+    pub fn reporting_ignored(&mut self, report: bool) {
+        self.report_ignored = report;
+    }
+
+    /// Gets the `(root, local)` paths which were ignored by globs or ignore files. Only recorded with `LaunchPad::reporting_ignored`.
+    /// Files underneath an ignored directory are not listed separately
+    pub fn ignored_paths(&self) -> &[(PathBuf, PathBuf)] {
+        &self.ignored
+    }
+
+    pub fn tree(&self) -> &Tree<A> {
         &self.tree
     }
 
@@ -182,69 +785,555 @@ impl<A: FileLoader> LaunchPad<A> where <A as FileLoader>::ErrorType: Debug {
         Orbit {
             physical: Tree::new(physical),
             patch: self.into_tree(),
-            virt
+            virt,
+            cache: None,
+            transformers: HashMap::new(),
+            layer_aliases: HashMap::new()
         }
     }
 }
 
+/// The layers of an `Orbit`, ordered from lowest to highest priority
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LayerKind {
+    Physical,
+    Patch,
+    Virtual
+}
+
+/// `Resolution` describes where `Orbit::load` will get the data for a local path from
+#[derive(Clone, Debug)]
+pub struct Resolution {
+    /// The layer that serves the path
+    pub layer: LayerKind,
+    /// The root path given to the layer's loader
+    pub root_path: PathBuf,
+    /// The local path given to the layer's loader
+    pub local_path: PathBuf,
+    /// The path as reported by `FileLoader::get_actual_path`
+    pub actual_path: Option<PathBuf>,
+    pub entry_type: Option<FileEntryType>,
+    /// The size of the data before any transformers are applied, see `Orbit::query_filesize`
+    pub size: Option<usize>,
+    /// The number of transformers registered for the path
+    pub transformers: usize,
+    /// Whether or not the data is currently held by the `ContentCache`
+    pub cached: bool
+}
+
 /// Orbit<A, B, C> handles the loading of files in the priority of C -> B -> A
 pub struct Orbit<A: FileLoader, B: FileLoader, C: FileLoader> {
     physical: Tree<A>,
     patch: Tree<B>,
-    virt: Tree<C>
+    virt: Tree<C>,
+    cache: Option<ContentCache>,
+    transformers: HashMap<PathBuf, Vec<Transformer<C::ErrorType>>>,
+    // Local paths which serve another local path from exactly one layer, see `Orbit::insert_layer_alias`
+    layer_aliases: HashMap<PathBuf, (LayerKind, PathBuf)>
 }
 
-/// OrbitError<A, B, C> is an enum type for the FileLoader::ErrorType of the Orbit loaders
-#[derive(Debug)]
+/// A `Transformer` is part of the virtual layer. It receives the local path and the data that would otherwise be loaded for it, and returns the new data
+pub type Transformer<E> = Box<dyn Fn(&Path, Vec<u8>) -> Result<Vec<u8>, E> + Send + Sync>;
+
+/// Error<A, B, C> is an enum type for the FileLoader::ErrorType of the Orbit loaders.
+/// It is also the `FileLoader::ErrorType` of an `Orbit` itself, which lets Orbits be nested inside of each other
+#[derive(Error, Debug)]
 pub enum Error<A: Debug, B: Debug, C: Debug> {
+    #[error("Physical layer failed: {0:?}")]
     Physical(A),
+    #[error("Patch layer failed: {0:?}")]
     Patch(B),
-    Virtual(C)
+    #[error("Virtual layer failed: {0:?}")]
+    Virtual(C),
+    /// The path is not provided by any layer, or it has been hidden by a whiteout or opaque directory in the patch layer
+    #[error("The path '{0}' was not found!")]
+    NotFound(PathBuf),
+    #[error("The path '{path}' of size {size:#x} does not fit into a buffer of size {capacity:#x}!")]
+    BufferTooSmall {
+        path: PathBuf,
+        size: usize,
+        capacity: usize
+    }
 }
 
+/// OrbitError<A, B, C> is the `Error` type produced by an `Orbit<A, B, C>`
+pub type OrbitError<A, B, C> = Error<<A as FileLoader>::ErrorType, <B as FileLoader>::ErrorType, <C as FileLoader>::ErrorType>;
+
+// Loaded data along with the layer which served it. The layer is `None` when the data is the output of transformers
+type LoadedData = (Vec<u8>, Option<LayerKind>);
+
 impl<A: FileLoader, B: FileLoader, C: FileLoader> Orbit<A, B, C> where
     <A as FileLoader>::ErrorType: Debug,
     <B as FileLoader>::ErrorType: Debug,
     <C as FileLoader>::ErrorType: Debug
 {
-    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, Error<A::ErrorType, B::ErrorType, C::ErrorType>> {
-        let path = path.as_ref();
+    fn load_with_layer(&self, path: &Path) -> Result<LoadedData, OrbitError<A, B, C>> {
+        let (data, layer) = self.load_untransformed(path)?;
+        match self.transformers.get(path) {
+            Some(transformers) => {
+                let mut data = data;
+                for transformer in transformers.iter() {
+                    data = transformer(path, data).map_err(Error::Virtual)?;
+                }
+                Ok((data, None))
+            },
+            None => Ok((data, Some(layer)))
+        }
+    }
+
+    fn layer_alias(&self, path: &Path) -> Option<(LayerKind, &Path)> {
+        self.layer_aliases.get(path).map(|(layer, target)| (*layer, target.as_path()))
+    }
+
+    // Aliased data is never cached, since the `ContentCache` only tracks changes to the path that it was loaded for and not to the target
+    fn is_aliased(&self, path: &Path) -> bool {
+        self.layer_aliases.contains_key(path)
+            || self.virt.get_alias(path).is_some()
+            || self.patch.get_alias(path).is_some()
+            || self.physical.get_alias(path).is_some()
+    }
+
+    // Loads an alias target from exactly one layer, without falling back to the layers below it
+    fn load_from_layer(&self, layer: LayerKind, path: &Path) -> Result<Vec<u8>, OrbitError<A, B, C>> {
+        let data = match layer {
+            LayerKind::Virtual => self.virt.load(path).map_err(Error::Virtual)?,
+            LayerKind::Patch => self.patch.load(path).map_err(Error::Patch)?,
+            LayerKind::Physical => self.physical.load(path).map_err(Error::Physical)?
+        };
+        data.ok_or_else(|| Error::NotFound(path.to_path_buf()))
+    }
+
+    fn open_from_layer(&self, layer: LayerKind, path: &Path) -> Result<Box<dyn ReadSeek + '_>, OrbitError<A, B, C>> {
+        let reader = match layer {
+            LayerKind::Virtual => self.virt.open(path).map_err(Error::Virtual)?,
+            LayerKind::Patch => self.patch.open(path).map_err(Error::Patch)?,
+            LayerKind::Physical => self.physical.open(path).map_err(Error::Physical)?
+        };
+        reader.ok_or_else(|| Error::NotFound(path.to_path_buf()))
+    }
+
+    fn read_from_layer(&self, layer: LayerKind, path: &Path, offset: u64, buf: &mut [u8]) -> Result<usize, OrbitError<A, B, C>> {
+        let count = match layer {
+            LayerKind::Virtual => self.virt.read_at(path, offset, buf).map_err(Error::Virtual)?,
+            LayerKind::Patch => self.patch.read_at(path, offset, buf).map_err(Error::Patch)?,
+            LayerKind::Physical => self.physical.read_at(path, offset, buf).map_err(Error::Physical)?
+        };
+        count.ok_or_else(|| Error::NotFound(path.to_path_buf()))
+    }
+
+    fn load_into_from_layer(&self, layer: LayerKind, path: &Path, buf: &mut [u8]) -> Result<usize, OrbitError<A, B, C>> {
+        let size = match layer {
+            LayerKind::Virtual => self.virt.load_into(path, buf).map_err(|e| Self::load_into_error(path, e, Error::Virtual))?,
+            LayerKind::Patch => self.patch.load_into(path, buf).map_err(|e| Self::load_into_error(path, e, Error::Patch))?,
+            LayerKind::Physical => self.physical.load_into(path, buf).map_err(|e| Self::load_into_error(path, e, Error::Physical))?
+        };
+        size.ok_or_else(|| Error::NotFound(path.to_path_buf()))
+    }
+
+    fn load_untransformed(&self, path: &Path) -> Result<(Vec<u8>, LayerKind), OrbitError<A, B, C>> {
+        if let Some((layer, target)) = self.layer_alias(path) {
+            return self.load_from_layer(layer, target).map(|data| (data, layer));
+        }
         match self.virt.load(path) {
-            Ok(Some(data)) => return Ok(data),
+            Ok(Some(data)) => return Ok((data, LayerKind::Virtual)),
             Ok(_) => {},
             Err(e) => return Err(Error::Virtual(e))
         }
-        self.load_patch(path)
+        match self.patch.load(path) {
+            Ok(Some(data)) => return Ok((data, LayerKind::Patch)),
+            Ok(_) => {},
+            Err(e) => return Err(Error::Patch(e))
+        }
+        if self.patch.is_masked(path) {
+            return Err(Error::NotFound(path.to_path_buf()));
+        }
+        self.load_physical(path).map(|data| (data, LayerKind::Physical))
     }
 
-    pub fn load_patch<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, Error<A::ErrorType, B::ErrorType, C::ErrorType>> {
+    /// Loads the path in the priority of virtual -> patch -> physical, and then runs any transformers registered for the path.
+    /// If a `ContentCache` is enabled, it is consulted first and is filled with the loaded data, unless the path is an alias
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, OrbitError<A, B, C>> {
+        let path = normalize(path.as_ref());
+        let path = path.as_ref();
+        if let Some(data) = self.synced_cache().and_then(|cache| cache.get(path)) {
+            return Ok(data);
+        }
+        let (data, layer) = self.load_with_layer(path)?;
+        if let Some(cache) = self.cache.as_ref().filter(|_| !self.is_aliased(path)) {
+            match layer {
+                Some(layer) => cache.insert(path, layer, &data),
+                None => cache.insert_transformed(path, &data)
+            }
+        }
+        Ok(data)
+    }
+
+    // Serves cached and transformed paths for `open`, `read_range` and `load_into` without copying the cached data
+    fn load_shared(&self, path: &Path) -> Result<Arc<[u8]>, OrbitError<A, B, C>> {
+        if let Some(data) = self.synced_cache().and_then(|cache| cache.get_shared(path)) {
+            return Ok(data);
+        }
+        let (data, layer) = self.load_with_layer(path)?;
+        let data: Arc<[u8]> = data.into();
+        if let Some(cache) = self.cache.as_ref().filter(|_| !self.is_aliased(path)) {
+            match layer {
+                Some(layer) => cache.insert_shared(path, layer, Arc::clone(&data)),
+                None => cache.insert_transformed_shared(path, Arc::clone(&data))
+            }
+        }
+        Ok(data)
+    }
+
+    pub fn load_patch<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, OrbitError<A, B, C>> {
         let path = path.as_ref();
         match self.patch.load(path) {
             Ok(Some(data)) => return Ok(data),
             Ok(_) => {},
             Err(e) => return Err(Error::Patch(e))
         }
+        if self.patch.is_masked(path) {
+            return Err(Error::NotFound(path.to_path_buf()));
+        }
         self.load_physical(path)
     }
 
-    pub fn load_physical<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, Error<A::ErrorType, B::ErrorType, C::ErrorType>> {
+    pub fn load_physical<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, OrbitError<A, B, C>> {
         let path = path.as_ref();
         match self.physical.load(path) {
-            Ok(data) => Ok(data.expect("Physical loader did not return valid file data!")),
+            Ok(Some(data)) => Ok(data),
+            Ok(None) => Err(Error::NotFound(path.to_path_buf())),
             Err(e) => Err(Error::Physical(e))
         }
     }
 
+    /// Opens the path for streaming, following the same priority as `Orbit::load`
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<Box<dyn ReadSeek + '_>, OrbitError<A, B, C>> {
+        let path = normalize(path.as_ref());
+        let path = path.as_ref();
+        if self.transformers.contains_key(path) || self.is_cached(path) {
+            return Ok(Box::new(Cursor::new(self.load_shared(path)?)));
+        }
+        if let Some((layer, target)) = self.layer_alias(path) {
+            return self.open_from_layer(layer, target);
+        }
+        match self.virt.open(path) {
+            Ok(Some(reader)) => return Ok(reader),
+            Ok(_) => {},
+            Err(e) => return Err(Error::Virtual(e))
+        }
+        self.open_patch(path)
+    }
+
+    pub fn open_patch<P: AsRef<Path>>(&self, path: P) -> Result<Box<dyn ReadSeek + '_>, OrbitError<A, B, C>> {
+        let path = path.as_ref();
+        match self.patch.open(path) {
+            Ok(Some(reader)) => return Ok(reader),
+            Ok(_) => {},
+            Err(e) => return Err(Error::Patch(e))
+        }
+        if self.patch.is_masked(path) {
+            return Err(Error::NotFound(path.to_path_buf()));
+        }
+        self.open_physical(path)
+    }
+
+    pub fn open_physical<P: AsRef<Path>>(&self, path: P) -> Result<Box<dyn ReadSeek + '_>, OrbitError<A, B, C>> {
+        let path = path.as_ref();
+        match self.physical.open(path) {
+            Ok(Some(reader)) => Ok(reader),
+            Ok(None) => Err(Error::NotFound(path.to_path_buf())),
+            Err(e) => Err(Error::Physical(e))
+        }
+    }
+
+    /// Reads part of the path into `buf` starting at `offset`, following the same priority as `Orbit::load`.
+    /// Returns the number of bytes read, which is only less than the length of `buf` when the end of the file is reached
+    pub fn read_range<P: AsRef<Path>>(&self, path: P, offset: u64, buf: &mut [u8]) -> Result<usize, OrbitError<A, B, C>> {
+        let path = normalize(path.as_ref());
+        let path = path.as_ref();
+        if self.transformers.contains_key(path) || self.is_cached(path) {
+            return Ok(copy_range(&self.load_shared(path)?, offset, buf));
+        }
+        if let Some((layer, target)) = self.layer_alias(path) {
+            return self.read_from_layer(layer, target, offset, buf);
+        }
+        match self.virt.read_at(path, offset, buf) {
+            Ok(Some(count)) => return Ok(count),
+            Ok(_) => {},
+            Err(e) => return Err(Error::Virtual(e))
+        }
+        self.read_range_patch(path, offset, buf)
+    }
+
+    pub fn read_range_patch<P: AsRef<Path>>(&self, path: P, offset: u64, buf: &mut [u8]) -> Result<usize, OrbitError<A, B, C>> {
+        let path = path.as_ref();
+        match self.patch.read_at(path, offset, buf) {
+            Ok(Some(count)) => return Ok(count),
+            Ok(_) => {},
+            Err(e) => return Err(Error::Patch(e))
+        }
+        if self.patch.is_masked(path) {
+            return Err(Error::NotFound(path.to_path_buf()));
+        }
+        self.read_range_physical(path, offset, buf)
+    }
+
+    pub fn read_range_physical<P: AsRef<Path>>(&self, path: P, offset: u64, buf: &mut [u8]) -> Result<usize, OrbitError<A, B, C>> {
+        let path = path.as_ref();
+        match self.physical.read_at(path, offset, buf) {
+            Ok(Some(count)) => Ok(count),
+            Ok(None) => Err(Error::NotFound(path.to_path_buf())),
+            Err(e) => Err(Error::Physical(e))
+        }
+    }
+
+    /// Loads the path into `buf`, following the same priority as `Orbit::load`, and returns the size of the file.
+    /// If the file does not fit, `Error::BufferTooSmall` is returned with the size of the file.
+    /// `Orbit::query_filesize` can be used to size the buffer ahead of time
+    pub fn load_into<P: AsRef<Path>>(&self, path: P, buf: &mut [u8]) -> Result<usize, OrbitError<A, B, C>> {
+        let path = normalize(path.as_ref());
+        let path = path.as_ref();
+        if self.transformers.contains_key(path) || self.is_cached(path) {
+            let data = self.load_shared(path)?;
+            if data.len() > buf.len() {
+                return Err(Error::BufferTooSmall { path: path.to_path_buf(), size: data.len(), capacity: buf.len() });
+            }
+            buf[..data.len()].copy_from_slice(&data);
+            return Ok(data.len());
+        }
+        if let Some((layer, target)) = self.layer_alias(path) {
+            return self.load_into_from_layer(layer, target, buf);
+        }
+        match self.virt.load_into(path, buf) {
+            Ok(Some(size)) => return Ok(size),
+            Ok(_) => {},
+            Err(e) => return Err(Self::load_into_error(path, e, Error::Virtual))
+        }
+        self.load_into_patch(path, buf)
+    }
+
+    pub fn load_into_patch<P: AsRef<Path>>(&self, path: P, buf: &mut [u8]) -> Result<usize, OrbitError<A, B, C>> {
+        let path = path.as_ref();
+        match self.patch.load_into(path, buf) {
+            Ok(Some(size)) => return Ok(size),
+            Ok(_) => {},
+            Err(e) => return Err(Self::load_into_error(path, e, Error::Patch))
+        }
+        if self.patch.is_masked(path) {
+            return Err(Error::NotFound(path.to_path_buf()));
+        }
+        self.load_into_physical(path, buf)
+    }
+
+    pub fn load_into_physical<P: AsRef<Path>>(&self, path: P, buf: &mut [u8]) -> Result<usize, OrbitError<A, B, C>> {
+        let path = path.as_ref();
+        match self.physical.load_into(path, buf) {
+            Ok(Some(size)) => Ok(size),
+            Ok(None) => Err(Error::NotFound(path.to_path_buf())),
+            Err(e) => Err(Self::load_into_error(path, e, Error::Physical))
+        }
+    }
+
+    fn load_into_error<E, F: FnOnce(E) -> OrbitError<A, B, C>>(path: &Path, error: LoadIntoError<E>, f: F) -> OrbitError<A, B, C> {
+        match error {
+            LoadIntoError::BufferTooSmall { size, capacity } => Error::BufferTooSmall {
+                path: path.to_path_buf(),
+                size,
+                capacity
+            },
+            LoadIntoError::Loader(e) => f(e)
+        }
+    }
+
+    fn resolve_in<L: FileLoader>(tree: &Tree<L>, layer: LayerKind, path: &Path) -> Option<Resolution> where <L as FileLoader>::ErrorType: Debug {
+        let (root_path, local_path) = tree.resolve(path)?;
+        Some(Resolution {
+            layer,
+            actual_path: tree.loader.get_actual_path(&root_path, &local_path),
+            entry_type: tree.get_path_type(path).ok(),
+            size: tree.loader.get_file_size(&root_path, &local_path),
+            root_path,
+            local_path,
+            transformers: 0,
+            cached: false
+        })
+    }
+
+    /// Finds out which layer, root, and actual path `Orbit::load` will use for the path, without loading any data
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> Option<Resolution> {
+        let path = normalize(path.as_ref());
+        let path = path.as_ref();
+        let mut resolution = match self.layer_alias(path) {
+            Some((LayerKind::Virtual, target)) => Self::resolve_in(&self.virt, LayerKind::Virtual, target)?,
+            Some((LayerKind::Patch, target)) => Self::resolve_in(&self.patch, LayerKind::Patch, target)?,
+            Some((LayerKind::Physical, target)) => Self::resolve_in(&self.physical, LayerKind::Physical, target)?,
+            None => Self::resolve_in(&self.virt, LayerKind::Virtual, path)
+                .or_else(|| Self::resolve_in(&self.patch, LayerKind::Patch, path))
+                .or_else(|| if self.patch.is_masked(path) {
+                    None
+                } else {
+                    Self::resolve_in(&self.physical, LayerKind::Physical, path)
+                })?
+        };
+        resolution.transformers = self.transformers.get(path).map(|transformers| transformers.len()).unwrap_or(0);
+        resolution.cached = self.is_cached(path);
+        Some(resolution)
+    }
+
+    // Transformed and cached files are served from the cached data by `open`, `read_range` and `load_into`
+    fn is_cached(&self, path: &Path) -> bool {
+        self.synced_cache().map(|cache| cache.contains(path)).unwrap_or(false)
+    }
+
+    fn generation(&self) -> u64 {
+        self.physical.generation()
+            .wrapping_add(self.patch.generation())
+            .wrapping_add(self.virt.generation())
+    }
+
+    // The `ContentCache` is cleared if a tree was modified without going through `Orbit` (i.e. through `Orbit::virt_mut`), so it never serves stale data
+    fn synced_cache(&self) -> Option<&ContentCache> {
+        let cache = self.cache.as_ref()?;
+        cache.sync(self.generation());
+        Some(cache)
+    }
+
+    // The methods which modify a tree through `Orbit` only invalidate the paths they affect, instead of clearing the whole `ContentCache`
+    fn invalidate_modified(&self, local_path: &Path) {
+        self.synced_cache();
+        self.invalidate_cached(local_path);
+    }
+
+    fn accept_modified(&self) {
+        if let Some(cache) = self.cache.as_ref() {
+            cache.set_generation(self.generation());
+        }
+    }
+
+    /// Enables the `ContentCache` for `Orbit::load`, `Orbit::open`, `Orbit::read_range` and `Orbit::load_into`, replacing the previous cache if there was one.
+    /// Entries in the cache are dropped if the trees have changed since they were loaded
+    pub fn enable_cache(&mut self, cache: ContentCache) {
+        cache.sync(self.generation());
+        self.cache = Some(cache);
+    }
+
+    /// Disables the `ContentCache`, returning it if there was one
+    pub fn disable_cache(&mut self) -> Option<ContentCache> {
+        self.cache.take()
+    }
+
+    pub fn cache(&self) -> Option<&ContentCache> {
+        self.cache.as_ref()
+    }
+
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Removes the path, and everything underneath it, from the `ContentCache`.
+    /// Modifications of the trees are picked up automatically, so this is only required when the data behind a path changes (i.e. a file is rewritten)
+    pub fn invalidate_cached<P: AsRef<Path>>(&self, local_path: P) {
+        if let Some(cache) = self.cache.as_ref() {
+            cache.invalidate(normalize(local_path.as_ref()));
+        }
+    }
+
     pub fn insert_virtual_file<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, root_path: P, local_path: Q) -> Option<(PathBuf, PathBuf)> {
-        self.virt.insert_file(root_path, local_path)
+        self.invalidate_modified(local_path.as_ref());
+        let replaced = self.virt.insert_file(root_path, local_path);
+        self.accept_modified();
+        replaced
     }
 
     pub fn insert_virtual_directory<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, root_path: P, local_path: Q) -> Option<(PathBuf, PathBuf)> {
-        self.virt.insert_directory(root_path, local_path)
+        self.invalidate_modified(local_path.as_ref());
+        let replaced = self.virt.insert_directory(root_path, local_path);
+        self.accept_modified();
+        replaced
     }
 
     pub fn insert_virtual_path<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, root_path: P, local_path: Q) -> Option<(PathBuf, PathBuf)> {
-        self.virt.insert_path(root_path, local_path)
+        self.invalidate_modified(local_path.as_ref());
+        let replaced = self.virt.insert_path(root_path, local_path);
+        self.accept_modified();
+        replaced
+    }
+
+    /// Inserts an alias into the virtual tree, see `Tree::insert_alias`
+    pub fn insert_virtual_alias<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, local_path: P, target: Q) -> Result<Option<(PathBuf, PathBuf)>, TreeError> {
+        self.invalidate_modified(local_path.as_ref());
+        let replaced = self.virt.insert_alias(local_path, target);
+        self.accept_modified();
+        replaced
+    }
+
+    /// Inserts an alias which makes the local path serve `target` from exactly one layer, returning the alias it replaced.
+    /// Layer aliases take priority over every layer (like transformers), are only followed by `Orbit::load` and the other virtual -> patch -> physical lookups,
+    /// and don't follow other layer aliases, so they can't form a cycle
+    pub fn insert_layer_alias<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, local_path: P, layer: LayerKind, target: Q) -> Option<(LayerKind, PathBuf)> {
+        let local_path = normalize(local_path.as_ref()).into_owned();
+        self.invalidate_cached(&local_path);
+        self.layer_aliases.insert(local_path, (layer, normalize(target.as_ref()).into_owned()))
+    }
+
+    /// Removes the layer alias for the path, returning it if there was one
+    pub fn remove_layer_alias<P: AsRef<Path>>(&mut self, local_path: P) -> Option<(LayerKind, PathBuf)> {
+        let local_path = normalize(local_path.as_ref());
+        self.invalidate_cached(&local_path);
+        self.layer_aliases.remove(local_path.as_ref())
+    }
+
+    pub fn get_layer_alias<P: AsRef<Path>>(&self, local_path: P) -> Option<(LayerKind, &Path)> {
+        self.layer_aliases.get(normalize(local_path.as_ref()).as_ref()).map(|(layer, target)| (*layer, target.as_path()))
+    }
+
+    /// Registers a transformer for the path. Transformers receive the data that `Orbit::load` would otherwise return, and are chained in registration order.
+    /// Errors returned by a transformer are reported as `Error::Virtual`
+    pub fn register_transformer<P: AsRef<Path>, F>(&mut self, local_path: P, transformer: F)
+    where
+        F: Fn(&Path, Vec<u8>) -> Result<Vec<u8>, C::ErrorType> + Send + Sync + 'static
+    {
+        let local_path = normalize(local_path.as_ref()).into_owned();
+        self.invalidate_cached(&local_path);
+        self.transformers
+            .entry(local_path)
+            .or_default()
+            .push(Box::new(transformer));
+    }
+
+    /// Removes every transformer registered for the path, returning how many were removed
+    pub fn remove_transformers<P: AsRef<Path>>(&mut self, local_path: P) -> usize {
+        let local_path = normalize(local_path.as_ref());
+        self.invalidate_cached(&local_path);
+        self.transformers.remove(local_path.as_ref()).map(|transformers| transformers.len()).unwrap_or(0)
+    }
+
+    pub fn has_transformers<P: AsRef<Path>>(&self, local_path: P) -> bool {
+        self.transformers.contains_key(normalize(local_path.as_ref()).as_ref())
+    }
+
+    /// Removes a path from the virtual tree, invalidating it in the `ContentCache`
+    pub fn remove_virtual_path<P: AsRef<Path>>(&mut self, local_path: P) -> Option<(PathBuf, PathBuf)> {
+        self.invalidate_modified(local_path.as_ref());
+        let removed = self.virt.remove_path(local_path);
+        self.accept_modified();
+        removed
+    }
+
+    /// Removes a path from the patch tree, invalidating it in the `ContentCache`
+    pub fn remove_patch_path<P: AsRef<Path>>(&mut self, local_path: P) -> Option<(PathBuf, PathBuf)> {
+        self.invalidate_modified(local_path.as_ref());
+        let removed = self.patch.remove_path(local_path);
+        self.accept_modified();
+        removed
+    }
+
+    /// Selects which candidate root serves a file in the patch tree (see `Tree::candidates`), invalidating it in the `ContentCache`
+    pub fn set_patch_candidate<P: AsRef<Path>>(&mut self, local_path: P, index: usize) -> bool {
+        self.invalidate_modified(local_path.as_ref());
+        let changed = self.patch.set_active_candidate(local_path, index);
+        self.accept_modified();
+        changed
     }
 
     pub fn walk_patch<F: FnMut(&Node, FileEntryType)>(&self, f: F) {
@@ -255,9 +1344,40 @@ impl<A: FileLoader, B: FileLoader, C: FileLoader> Orbit<A, B, C> where
         self.virt.walk_paths(f);
     }
 
+    /// Gets the size of the data that `Orbit::load` returns for the path. Any path without transformers is measured by the loader which serves it (see `Orbit::resolve`).
+    /// Paths with transformers are measured from the `ContentCache` when their output is cached. Otherwise they are loaded and transformed,
+    /// which costs as much as `Orbit::load` does, but neither fills the cache nor counts towards its statistics
+    pub fn query_filesize<P: AsRef<Path>>(&self, local_path: P) -> Option<usize> {
+        let local_path = normalize(local_path.as_ref());
+        let local_path = local_path.as_ref();
+        if self.has_transformers(local_path) {
+            if let Some(size) = self.synced_cache().and_then(|cache| cache.cached_size(local_path)) {
+                return Some(size);
+            }
+            self.load_with_layer(local_path).ok().map(|(data, _)| data.len())
+        } else {
+            self.resolve(local_path)?.size
+        }
+    }
+
+    /// Gets the largest filesize of the path in the virtual and patch trees and in the physical tree, along with its size after transformers.
+    /// Only paths which are in a tree are included, so a file which the physical loader finds without it being in the physical tree is not,
+    /// see `Orbit::physical_filesize`
     pub fn query_max_filesize<P: AsRef<Path>>(&self, local_path: P) -> Option<usize> {
         let local_path = local_path.as_ref();
-        self.query_max_layered_filesize(local_path).max(self.physical.query_filesize(local_path))
+        let transformed = if self.has_transformers(local_path) {
+            self.query_filesize(local_path)
+        } else {
+            None
+        };
+        let size = match self.layer_alias(local_path) {
+            Some((LayerKind::Virtual, target)) => self.virt.query_filesize(target),
+            Some((LayerKind::Patch, target)) => self.patch.query_filesize(target),
+            Some((LayerKind::Physical, target)) => self.physical.query_filesize(target),
+            None if self.patch.is_masked(local_path) => self.query_max_layered_filesize(local_path),
+            None => self.query_max_layered_filesize(local_path).max(self.physical.query_filesize(local_path))
+        };
+        size.max(transformed)
     }
 
     pub fn query_max_layered_filesize<P: AsRef<Path>>(&self, local_path: P) -> Option<usize> {
@@ -267,8 +1387,19 @@ impl<A: FileLoader, B: FileLoader, C: FileLoader> Orbit<A, B, C> where
 
     pub fn query_actual_path<P: AsRef<Path>>(&self, local_path: P) -> Option<PathBuf> {
         let local_path = local_path.as_ref();
+        if let Some((layer, target)) = self.layer_alias(local_path) {
+            return match layer {
+                LayerKind::Virtual => self.virt.get_full_path(target),
+                LayerKind::Patch => self.patch.get_full_path(target),
+                LayerKind::Physical => self.physical.get_full_path(target)
+            };
+        }
         self.query_actual_layered_path(local_path)
-            .or(self.physical.get_full_path(local_path))
+            .or_else(|| if self.patch.is_masked(local_path) {
+                None
+            } else {
+                self.physical.get_full_path(local_path)
+            })
     }
 
     pub fn query_actual_layered_path<P: AsRef<Path>>(&self, local_path: P) -> Option<PathBuf> {
@@ -277,6 +1408,7 @@ impl<A: FileLoader, B: FileLoader, C: FileLoader> Orbit<A, B, C> where
             .or(self.patch.get_full_path(local_path))
     }
 
+    /// Gets the filesize of the path from the physical loader, whether or not the path is in the physical tree
     pub fn physical_filesize<P: AsRef<Path>>(&self, local_path: P) -> Option<usize> {
         self.physical.query_filesize_local(local_path)
     }
@@ -301,11 +1433,11 @@ impl<A: FileLoader, B: FileLoader, C: FileLoader> Orbit<A, B, C> where
         self.virt.get_path_type(local_path)
     }
 
-    pub fn get_children<'a, P: AsRef<Path>>(&'a self, local_path: P) -> HashSet<&'a Path> {
+    pub fn get_children<P: AsRef<Path>>(&self, local_path: P) -> HashSet<&Path> {
         let local_path = local_path.as_ref();
         let virt = self.virt.get_children(local_path);
         let mut patch = self.patch.get_children(local_path);
-        patch.extend(virt.into_iter());
+        patch.extend(virt);
         patch
     }
 
@@ -315,15 +1447,89 @@ impl<A: FileLoader, B: FileLoader, C: FileLoader> Orbit<A, B, C> where
         self.virt.contains_path(local_path) || self.patch.contains_path(local_path)
     }
 
-    pub fn patch<'a>(&'a self) -> &'a Tree<B> {
+    pub fn patch(&self) -> &Tree<B> {
         &self.patch
     }
 
-    pub fn virt<'a>(&'a self) -> &'a Tree<C> {
+    pub fn virt(&self) -> &Tree<C> {
         &self.virt
     }
 
-    pub fn virt_mut<'a>(&'a mut self) -> &'a mut Tree<C> {
+    /// Mutable access to the virtual tree. Any change made here clears the whole `ContentCache`, while the `Orbit::insert_virtual_*` and
+    /// `Orbit::remove_virtual_path` methods only invalidate the paths they affect
+    pub fn virt_mut(&mut self) -> &mut Tree<C> {
         &mut self.virt
     }
+}
+
+/// An `Orbit` is a `FileLoader` for its own layered filesystem, so it can be used as a layer of another `Orbit` (i.e. as the physical layer in `LaunchPad::launch`).
+/// The root path and local path given to the loader are joined and treated as a local path of this `Orbit`
+impl<A: FileLoader, B: FileLoader, C: FileLoader> FileLoader for Orbit<A, B, C> where
+    <A as FileLoader>::ErrorType: Debug,
+    <B as FileLoader>::ErrorType: Debug,
+    <C as FileLoader>::ErrorType: Debug
+{
+    type ErrorType = OrbitError<A, B, C>;
+
+    fn path_exists(&self, root_path: &Path, local_path: &Path) -> bool {
+        self.resolve(root_path.join(local_path)).is_some()
+    }
+
+    fn get_file_size(&self, root_path: &Path, local_path: &Path) -> Option<usize> {
+        self.query_filesize(root_path.join(local_path))
+    }
+
+    fn get_path_type(&self, root_path: &Path, local_path: &Path) -> Result<FileEntryType, Self::ErrorType> {
+        let path = root_path.join(local_path);
+        match self.resolve(&path).and_then(|resolution| resolution.entry_type) {
+            Some(entry_type) => Ok(entry_type),
+            None => Err(Error::NotFound(path))
+        }
+    }
+
+    fn load_path(&self, root_path: &Path, local_path: &Path) -> Result<Vec<u8>, Self::ErrorType> {
+        self.load(root_path.join(local_path))
+    }
+
+    fn open(&self, root_path: &Path, local_path: &Path) -> Result<Box<dyn ReadSeek + '_>, Self::ErrorType> {
+        Orbit::open(self, root_path.join(local_path))
+    }
+
+    fn read_at(&self, root_path: &Path, local_path: &Path, offset: u64, buf: &mut [u8]) -> Result<usize, Self::ErrorType> {
+        self.read_range(root_path.join(local_path), offset, buf)
+    }
+
+    fn load_into(&self, root_path: &Path, local_path: &Path, buf: &mut [u8]) -> Result<usize, LoadIntoError<Self::ErrorType>> {
+        match Orbit::load_into(self, root_path.join(local_path), buf) {
+            Ok(size) => Ok(size),
+            Err(Error::BufferTooSmall { size, capacity, .. }) => Err(LoadIntoError::BufferTooSmall { size, capacity }),
+            Err(e) => Err(LoadIntoError::Loader(e))
+        }
+    }
+
+    fn get_actual_path(&self, root_path: &Path, local_path: &Path) -> Option<PathBuf> {
+        self.resolve(root_path.join(local_path))?.actual_path
+    }
+}
+
+impl<A, B, C> Orbit<A, B, C>
+where
+    A: FileLoader + Send + 'static,
+    B: FileLoader + Send + 'static,
+    C: FileLoader + Send + 'static,
+    <A as FileLoader>::ErrorType: Debug + Send + Sync + 'static,
+    <B as FileLoader>::ErrorType: Debug + Send + Sync + 'static,
+    <C as FileLoader>::ErrorType: Debug + Send + Sync + 'static
+{
+    /// Converts this `Orbit` into a `LayeredOrbit` with the layers `physical`, `patch`, and `virtual` (from bottom to top).
+    /// The `ContentCache`, any registered transformers and any layer aliases (see `Orbit::insert_layer_alias`) are dropped,
+    /// so paths which rely on them load differently from the `LayeredOrbit`. Aliases inside of the trees are kept
+    pub fn into_layered(self) -> LayeredOrbit {
+        let Self { physical, patch, virt, .. } = self;
+        let mut layered = LayeredOrbit::new();
+        layered.push_layer("physical", physical);
+        layered.push_layer("patch", patch);
+        layered.push_layer("virtual", virt);
+        layered
+    }
 }
\ No newline at end of file
@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::LayerKind;
+
+/// Hit/miss statistics for a `ContentCache`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// The number of bytes currently held by the cache
+    pub size: usize,
+    /// The number of files currently held by the cache
+    pub count: usize
+}
+
+struct CacheEntry {
+    data: Arc<[u8]>,
+    tick: u64
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<PathBuf, CacheEntry>,
+    order: BTreeMap<u64, PathBuf>,
+    tick: u64,
+    stats: CacheStats,
+    // The generation of the trees that the entries were loaded from, see `Tree::generation`
+    generation: u64
+}
+
+impl CacheState {
+    fn remove(&mut self, path: &Path) {
+        if let Some(entry) = self.entries.remove(path) {
+            self.order.remove(&entry.tick);
+            self.stats.size -= entry.data.len();
+            self.stats.count -= 1;
+        }
+    }
+}
+
+/// `ContentCache` is an LRU cache of file contents which can be put in front of `Orbit::load`, `Orbit::open`, `Orbit::read_range` and `Orbit::load_into`.
+/// Only `Orbit::load` fills the cache, and the paths are used as keys exactly as they are given (`Orbit` normalizes them first).
+/// Only files which are served by an enabled layer are cached. By default the physical and patch layers are cached,
+/// while the virtual layer is not since its data is usually generated on the fly.
+/// The output of transformers (see `Orbit::register_transformer`) is cached regardless of the layer it came from, unless `ContentCache::cache_transformed` disables it.
+pub struct ContentCache {
+    budget: usize,
+    physical: bool,
+    patch: bool,
+    virt: bool,
+    transformed: bool,
+    state: Mutex<CacheState>
+}
+
+impl ContentCache {
+    /// Creates a new cache which holds at most `budget` bytes of file data
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            physical: true,
+            patch: true,
+            virt: false,
+            transformed: true,
+            state: Mutex::new(CacheState::default())
+        }
+    }
+
+    /// Sets whether or not files served by `layer` should be cached
+    pub fn cache_layer(&mut self, layer: LayerKind, enabled: bool) {
+        match layer {
+            LayerKind::Physical => self.physical = enabled,
+            LayerKind::Patch => self.patch = enabled,
+            LayerKind::Virtual => self.virt = enabled
+        }
+    }
+
+    pub fn is_layer_cached(&self, layer: LayerKind) -> bool {
+        match layer {
+            LayerKind::Physical => self.physical,
+            LayerKind::Patch => self.patch,
+            LayerKind::Virtual => self.virt
+        }
+    }
+
+    /// Sets whether or not the output of transformers should be cached
+    pub fn cache_transformed(&mut self, enabled: bool) {
+        self.transformed = enabled;
+    }
+
+    pub fn is_transformed_cached(&self) -> bool {
+        self.transformed
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Checks if the path is cached, without affecting the LRU order or statistics
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.state.lock().unwrap().entries.contains_key(path.as_ref())
+    }
+
+    /// Gets the size of the cached data for the path, without affecting the LRU order or statistics
+    pub fn cached_size<P: AsRef<Path>>(&self, path: P) -> Option<usize> {
+        self.state.lock().unwrap().entries.get(path.as_ref()).map(|entry| entry.data.len())
+    }
+
+    /// Gets a copy of the cached data for the path, marking it as the most recently used entry
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<Vec<u8>> {
+        self.get_shared(path).map(|data| data.to_vec())
+    }
+
+    /// Gets the cached data for the path without copying it, marking it as the most recently used entry
+    pub fn get_shared<P: AsRef<Path>>(&self, path: P) -> Option<Arc<[u8]>> {
+        let path = path.as_ref();
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        let old_tick = match state.entries.get_mut(path) {
+            Some(entry) => std::mem::replace(&mut entry.tick, tick),
+            None => {
+                state.stats.misses += 1;
+                return None;
+            }
+        };
+        let key = state.order.remove(&old_tick).expect("Cache entry is missing from the LRU order!");
+        state.order.insert(tick, key);
+        state.stats.hits += 1;
+        state.entries.get(path).map(|entry| Arc::clone(&entry.data))
+    }
+
+    /// Inserts the data for a path which was served by `layer`, evicting the least recently used entries until it fits.
+    /// Data from a disabled layer, or data larger than the budget, is not cached.
+    pub fn insert<P: AsRef<Path>>(&self, path: P, layer: LayerKind, data: &[u8]) {
+        if self.is_layer_cached(layer) && data.len() <= self.budget {
+            self.insert_shared(path, layer, Arc::from(data));
+        }
+    }
+
+    /// Inserts data for a path without copying it, following the same rules as `ContentCache::insert`
+    pub fn insert_shared<P: AsRef<Path>>(&self, path: P, layer: LayerKind, data: Arc<[u8]>) {
+        if self.is_layer_cached(layer) {
+            self.store(path.as_ref(), data);
+        }
+    }
+
+    /// Inserts the output of the transformers for a path, following the same rules as `ContentCache::insert` except that
+    /// it is cached when `ContentCache::is_transformed_cached` is set instead of depending on a layer
+    pub fn insert_transformed<P: AsRef<Path>>(&self, path: P, data: &[u8]) {
+        if self.transformed && data.len() <= self.budget {
+            self.store(path.as_ref(), Arc::from(data));
+        }
+    }
+
+    /// Inserts the output of the transformers for a path without copying it, see `ContentCache::insert_transformed`
+    pub fn insert_transformed_shared<P: AsRef<Path>>(&self, path: P, data: Arc<[u8]>) {
+        if self.transformed {
+            self.store(path.as_ref(), data);
+        }
+    }
+
+    fn store(&self, path: &Path, data: Arc<[u8]>) {
+        if data.len() > self.budget {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.remove(path);
+        while state.stats.size + data.len() > self.budget {
+            let oldest = match state.order.values().next() {
+                Some(oldest) => oldest.clone(),
+                None => break
+            };
+            state.remove(&oldest);
+            state.stats.evictions += 1;
+        }
+        state.tick += 1;
+        let tick = state.tick;
+        state.order.insert(tick, path.to_path_buf());
+        state.stats.size += data.len();
+        state.entries.insert(path.to_path_buf(), CacheEntry {
+            data,
+            tick
+        });
+        state.stats.count += 1;
+    }
+
+    /// Removes the path, and every cached path underneath it, from the cache
+    pub fn invalidate<P: AsRef<Path>>(&self, path: P) {
+        let path = path.as_ref();
+        let mut state = self.state.lock().unwrap();
+        let stale: Vec<PathBuf> = state.entries
+            .keys()
+            .filter(|cached| cached.starts_with(path))
+            .cloned()
+            .collect();
+        for cached in stale.iter() {
+            state.remove(cached);
+        }
+    }
+
+    // Drops every entry if the trees were modified since the entries were loaded
+    pub(crate) fn sync(&self, generation: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation {
+            state.generation = generation;
+            state.entries.clear();
+            state.order.clear();
+            state.stats.size = 0;
+            state.stats.count = 0;
+        }
+    }
+
+    // Accepts a modification of the trees whose affected paths were already invalidated
+    pub(crate) fn set_generation(&self, generation: u64) {
+        self.state.lock().unwrap().generation = generation;
+    }
+
+    /// Removes every entry from the cache. The hit/miss statistics are kept
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.order.clear();
+        state.stats.size = 0;
+        state.stats.count = 0;
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.state.lock().unwrap().stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eviction_order_test() {
+        let cache = ContentCache::new(10);
+        cache.insert("a", LayerKind::Patch, b"aaaa");
+        cache.insert("b", LayerKind::Patch, b"bbbb");
+        // Checking for a path doesn't count as a use, but getting it does
+        assert!(cache.contains("a"));
+        assert!(cache.get_shared("a").unwrap().as_ref() == b"aaaa");
+        cache.insert("c", LayerKind::Physical, b"cccc");
+        assert!(cache.contains("a") && !cache.contains("b") && cache.contains("c"));
+
+        // Replacing a path moves it to the back of the order without evicting anything
+        cache.insert("a", LayerKind::Patch, b"AAAA");
+        assert!(cache.stats().evictions == 1);
+        cache.insert("d", LayerKind::Patch, b"dddd");
+        assert!(!cache.contains("c") && cache.get("a").unwrap() == b"AAAA");
+        assert!(cache.stats() == CacheStats { hits: 2, misses: 0, evictions: 2, size: 8, count: 2 });
+
+        // Data which can never fit, or which comes from a layer that isn't cached, leaves the cache untouched
+        cache.insert("e", LayerKind::Patch, b"eeeeeeeeeee");
+        cache.insert("f", LayerKind::Virtual, b"f");
+        cache.insert_transformed("g", b"g");
+        assert!(!cache.contains("e") && !cache.contains("f") && cache.contains("g"));
+        assert!(cache.stats().count == 3 && cache.cached_size("a") == Some(4));
+    }
+}
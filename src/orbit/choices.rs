@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ChoicesError {
+    #[error("Failed to read or write conflict choices! io::Error: {0:?}")]
+    Io(#[from] io::Error),
+    #[error("Invalid conflict choice on line {line}: '{content}'")]
+    InvalidLine {
+        line: usize,
+        content: String
+    },
+    #[error("The path '{}' can't be written to a resolution file because it is not valid UTF-8!", .0.display())]
+    NonUtf8Path(PathBuf)
+}
+
+// Tabs and newlines separate the choices, so they are escaped along with the escape character itself.
+// `#` is escaped so that a path can't start a comment
+fn escape(path: &Path) -> Result<String, ChoicesError> {
+    let path = path.to_str().ok_or_else(|| ChoicesError::NonUtf8Path(path.to_path_buf()))?;
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '#' => escaped.push_str("\\#"),
+            c => escaped.push(c)
+        }
+    }
+    Ok(escaped)
+}
+
+fn unescape(field: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            't' => unescaped.push('\t'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            '#' => unescaped.push('#'),
+            _ => return None
+        }
+    }
+    Some(unescaped)
+}
+
+/// The user's choices of which root serves a local path when there is a conflict, consulted by `LaunchPad` before its resolver.
+///
+/// Choices are stored as text, one per line, with the local path and the root separated by a tab.
+/// Blank lines and lines starting with `#` are ignored. Backslashes, tabs, newlines, carriage returns and `#` in paths are escaped with a backslash
+/// (`\\`, `\t`, `\n`, `\r` and `\#`), and paths have to be valid UTF-8.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConflictChoices {
+    choices: BTreeMap<PathBuf, PathBuf>
+}
+
+impl ConflictChoices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the choices from a resolution file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ChoicesError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Writes the choices to a resolution file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ChoicesError> {
+        fs::write(path, self.to_text()?)?;
+        Ok(())
+    }
+
+    /// Writes the choices in the resolution file format, which can be read back with `str::parse`.
+    /// Returns `ChoicesError::NonUtf8Path` if a path is not valid UTF-8
+    pub fn to_text(&self) -> Result<String, ChoicesError> {
+        let mut text = String::from("# orbits conflict choices: <local path>\t<root path>\n");
+        for (local, root) in self.choices.iter() {
+            text.push_str(&escape(local)?);
+            text.push('\t');
+            text.push_str(&escape(root)?);
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    /// Chooses the root which serves the local path, returning the previous choice
+    pub fn set<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, local_path: P, root_path: Q) -> Option<PathBuf> {
+        self.choices.insert(local_path.as_ref().to_path_buf(), root_path.as_ref().to_path_buf())
+    }
+
+    pub fn get<P: AsRef<Path>>(&self, local_path: P) -> Option<&Path> {
+        self.choices.get(local_path.as_ref()).map(PathBuf::as_path)
+    }
+
+    pub fn remove<P: AsRef<Path>>(&mut self, local_path: P) -> Option<PathBuf> {
+        self.choices.remove(local_path.as_ref())
+    }
+
+    /// Iterates over every `(local, root)` choice, ordered by local path
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, &Path)> {
+        self.choices.iter().map(|(local, root)| (local.as_path(), root.as_path()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.choices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.choices.is_empty()
+    }
+
+    /// Gets every `(local, root)` choice whose root no longer exists on disk
+    pub fn stale(&self) -> Vec<(PathBuf, PathBuf)> {
+        self.choices
+            .iter()
+            .filter(|(_, root)| !root.exists())
+            .map(|(local, root)| (local.clone(), root.clone()))
+            .collect()
+    }
+}
+
+impl FromStr for ConflictChoices {
+    type Err = ChoicesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut choices = Self::new();
+        for (index, line) in s.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let fields = line
+                .split_once('\t')
+                .and_then(|(local, root)| Some((unescape(local)?, unescape(root)?)));
+            match fields {
+                Some((local, root)) if !local.is_empty() && !root.is_empty() => {
+                    choices.set(local, root);
+                },
+                _ => return Err(ChoicesError::InvalidLine {
+                    line: index + 1,
+                    content: line.to_string()
+                })
+            }
+        }
+        Ok(choices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_round_trip_test() {
+        for path in ["plain/path.prc", "tab\there", "new\nline\r", "back\\slash\\", "#comment", "\\#", "\\t"].iter() {
+            let escaped = escape(Path::new(path)).unwrap();
+            assert!(!escaped.contains(['\t', '\n', '\r']) && !escaped.replace("\\#", "").contains('#'));
+            assert!(unescape(&escaped).as_deref() == Some(*path));
+        }
+        assert!(unescape("dangling\\").is_none());
+        assert!(unescape("unknown\\x").is_none());
+
+        let mut choices = ConflictChoices::new();
+        choices.set("#ui/param\t.prc", "mods\\a\nb");
+        choices.set(" #leading", "mods/b");
+        let text = choices.to_text().unwrap();
+        assert!(text.lines().count() == 3);
+        let parsed: ConflictChoices = text.parse().unwrap();
+        assert!(parsed.get("#ui/param\t.prc") == Some(Path::new("mods\\a\nb")));
+        assert!(parsed.get(" #leading") == Some(Path::new("mods/b")));
+    }
+}
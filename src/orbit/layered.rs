@@ -0,0 +1,355 @@
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use crate::FileEntryType;
+use crate::loader::{FileLoader, LoadIntoError, ReadSeek};
+use crate::tree::{Tree, node::Node};
+
+/// Type-erased `FileLoader::ErrorType` returned by a `Layer`
+pub type LayerErrorType = Box<dyn Debug + Send + Sync>;
+
+/// The `Layer` trait is the type-erased view of a single layer inside of a `LayeredOrbit`.
+/// It is implemented for every `Tree` whose loader error is `Debug + Send + Sync`, but can be implemented manually
+/// for anything else that can resolve local paths.
+pub trait Layer {
+    /// Attempts to load the local path. `Ok(None)` means the layer does not provide the path and the next layer should be consulted
+    fn load(&self, local_path: &Path) -> Result<Option<Vec<u8>>, LayerErrorType>;
+    /// Attempts to open the local path for streaming, following the same rules as `Layer::load`
+    fn open(&self, local_path: &Path) -> Result<Option<Box<dyn ReadSeek + '_>>, LayerErrorType>;
+    /// Attempts to read part of the local path into `buf`, following the same rules as `Layer::load`
+    fn read_at(&self, local_path: &Path, offset: u64, buf: &mut [u8]) -> Result<Option<usize>, LayerErrorType>;
+    /// Attempts to load the local path into `buf`, following the same rules as `Layer::load`
+    fn load_into(&self, local_path: &Path, buf: &mut [u8]) -> Result<Option<usize>, LoadIntoError<LayerErrorType>>;
+    fn contains(&self, local_path: &Path) -> bool;
+    fn query_filesize(&self, local_path: &Path) -> Option<usize>;
+    fn get_full_path(&self, local_path: &Path) -> Option<PathBuf>;
+    fn get_path_type(&self, local_path: &Path) -> Result<FileEntryType, LayerErrorType>;
+    fn get_children(&self, local_path: &Path) -> HashSet<&Path>;
+    fn walk_paths(&self, f: &mut dyn FnMut(&Node, FileEntryType));
+    /// Checks if the layer hides the local path from every layer below it, i.e. with a whiteout or an opaque directory
+    fn is_whiteout(&self, _local_path: &Path) -> bool {
+        false
+    }
+}
+
+impl<L: FileLoader> Layer for Tree<L> where <L as FileLoader>::ErrorType: Debug + Send + Sync + 'static {
+    fn load(&self, local_path: &Path) -> Result<Option<Vec<u8>>, LayerErrorType> {
+        Tree::load(self, local_path).map_err(|e| Box::new(e) as LayerErrorType)
+    }
+
+    fn open(&self, local_path: &Path) -> Result<Option<Box<dyn ReadSeek + '_>>, LayerErrorType> {
+        Tree::open(self, local_path).map_err(|e| Box::new(e) as LayerErrorType)
+    }
+
+    fn read_at(&self, local_path: &Path, offset: u64, buf: &mut [u8]) -> Result<Option<usize>, LayerErrorType> {
+        Tree::read_at(self, local_path, offset, buf).map_err(|e| Box::new(e) as LayerErrorType)
+    }
+
+    fn load_into(&self, local_path: &Path, buf: &mut [u8]) -> Result<Option<usize>, LoadIntoError<LayerErrorType>> {
+        Tree::load_into(self, local_path, buf).map_err(|e| match e {
+            LoadIntoError::BufferTooSmall { size, capacity } => LoadIntoError::BufferTooSmall { size, capacity },
+            LoadIntoError::Loader(e) => LoadIntoError::Loader(Box::new(e) as LayerErrorType)
+        })
+    }
+
+    fn contains(&self, local_path: &Path) -> bool {
+        self.contains_path(local_path)
+    }
+
+    // Only paths in the tree are sized. Handing the bare local path to the loader (like `Tree::load` does) would let a
+    // `StandardLoader` report the size of a file relative to the working directory
+    fn query_filesize(&self, local_path: &Path) -> Option<usize> {
+        Tree::query_filesize(self, local_path)
+    }
+
+    fn get_full_path(&self, local_path: &Path) -> Option<PathBuf> {
+        Tree::get_full_path(self, local_path)
+    }
+
+    fn get_path_type(&self, local_path: &Path) -> Result<FileEntryType, LayerErrorType> {
+        Tree::get_path_type(self, local_path).map_err(|e| Box::new(e) as LayerErrorType)
+    }
+
+    fn get_children(&self, local_path: &Path) -> HashSet<&Path> {
+        Tree::get_children(self, local_path)
+    }
+
+    fn walk_paths(&self, f: &mut dyn FnMut(&Node, FileEntryType)) {
+        Tree::walk_paths(self, f)
+    }
+
+    fn is_whiteout(&self, local_path: &Path) -> bool {
+        Tree::is_masked(self, local_path)
+    }
+}
+
+/// Layer-indexed error type for a `LayeredOrbit`. The index is the position of the layer in the stack, where `0` is the bottom layer.
+#[derive(Error, Debug)]
+pub enum LayerError {
+    #[error("Layer {index} ('{name}') failed: {error:?}")]
+    Layer {
+        index: usize,
+        name: String,
+        error: LayerErrorType
+    },
+    #[error("The path '{0}' is not provided by any layer!")]
+    NotFound(PathBuf),
+    #[error("The path '{path}' of size {size:#x} does not fit into a buffer of size {capacity:#x}!")]
+    BufferTooSmall {
+        path: PathBuf,
+        size: usize,
+        capacity: usize
+    }
+}
+
+struct NamedLayer {
+    name: String,
+    layer: Box<dyn Layer + Send>
+}
+
+/// `LayeredOrbit` is the N-layer equivalent of `Orbit<A, B, C>`.
+/// Layers are pushed bottom to top, and files are loaded from the top of the stack downwards.
+#[derive(Default)]
+pub struct LayeredOrbit {
+    layers: Vec<NamedLayer>
+}
+
+impl LayeredOrbit {
+    fn error(&self, index: usize, error: LayerErrorType) -> LayerError {
+        LayerError::Layer {
+            index,
+            name: self.layers[index].name.clone(),
+            error
+        }
+    }
+
+    /// Creates an empty stack
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new()
+        }
+    }
+
+    /// Pushes a layer on top of the stack, giving it the highest priority. Returns the index of the new layer.
+    pub fn push_layer<S: Into<String>, L: Layer + Send + 'static>(&mut self, name: S, layer: L) -> usize {
+        self.layers.push(NamedLayer {
+            name: name.into(),
+            layer: Box::new(layer)
+        });
+        self.layers.len() - 1
+    }
+
+    /// Removes and returns the top layer of the stack
+    pub fn pop_layer(&mut self) -> Option<(String, Box<dyn Layer + Send>)> {
+        self.layers.pop().map(|NamedLayer { name, layer }| (name, layer))
+    }
+
+    /// Gets the index of the layer with the specified name
+    pub fn layer_index(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    pub fn layer(&self, index: usize) -> Option<&(dyn Layer + Send)> {
+        self.layers.get(index).map(|layer| layer.layer.as_ref())
+    }
+
+    pub fn layer_by_name(&self, name: &str) -> Option<&(dyn Layer + Send)> {
+        self.layer(self.layer_index(name)?)
+    }
+
+    pub fn layer_name(&self, index: usize) -> Option<&str> {
+        self.layers.get(index).map(|layer| layer.name.as_str())
+    }
+
+    /// The names of every layer, from the bottom of the stack to the top
+    pub fn layer_names(&self) -> Vec<&str> {
+        self.layers.iter().map(|layer| layer.name.as_str()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Loads the path from the highest priority layer that provides it
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, LayerError> {
+        match self.layers.len() {
+            0 => Err(LayerError::NotFound(path.as_ref().to_path_buf())),
+            len => self.load_from(len - 1, path)
+        }
+    }
+
+    /// Loads the path starting at the layer `index` and working down the stack, skipping every layer above it.
+    /// This is the equivalent of `Orbit::load_patch` and `Orbit::load_physical`.
+    pub fn load_from<P: AsRef<Path>>(&self, index: usize, path: P) -> Result<Vec<u8>, LayerError> {
+        let path = path.as_ref();
+        for (index, layer) in self.layers.iter().enumerate().take(index + 1).rev() {
+            match layer.layer.load(path) {
+                Ok(Some(data)) => return Ok(data),
+                Ok(_) => {},
+                Err(e) => return Err(self.error(index, e))
+            }
+            if layer.layer.is_whiteout(path) {
+                break;
+            }
+        }
+        Err(LayerError::NotFound(path.to_path_buf()))
+    }
+
+    /// Opens the path for streaming from the highest priority layer that provides it
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<Box<dyn ReadSeek + '_>, LayerError> {
+        match self.layers.len() {
+            0 => Err(LayerError::NotFound(path.as_ref().to_path_buf())),
+            len => self.open_from(len - 1, path)
+        }
+    }
+
+    /// Opens the path starting at the layer `index` and working down the stack
+    pub fn open_from<P: AsRef<Path>>(&self, index: usize, path: P) -> Result<Box<dyn ReadSeek + '_>, LayerError> {
+        let path = path.as_ref();
+        for (index, layer) in self.layers.iter().enumerate().take(index + 1).rev() {
+            match layer.layer.open(path) {
+                Ok(Some(reader)) => return Ok(reader),
+                Ok(_) => {},
+                Err(e) => return Err(self.error(index, e))
+            }
+            if layer.layer.is_whiteout(path) {
+                break;
+            }
+        }
+        Err(LayerError::NotFound(path.to_path_buf()))
+    }
+
+    /// Reads part of the path into `buf` from the highest priority layer that provides it
+    pub fn read_range<P: AsRef<Path>>(&self, path: P, offset: u64, buf: &mut [u8]) -> Result<usize, LayerError> {
+        match self.layers.len() {
+            0 => Err(LayerError::NotFound(path.as_ref().to_path_buf())),
+            len => self.read_range_from(len - 1, path, offset, buf)
+        }
+    }
+
+    /// Reads part of the path into `buf` starting at the layer `index` and working down the stack
+    pub fn read_range_from<P: AsRef<Path>>(&self, index: usize, path: P, offset: u64, buf: &mut [u8]) -> Result<usize, LayerError> {
+        let path = path.as_ref();
+        for (index, layer) in self.layers.iter().enumerate().take(index + 1).rev() {
+            match layer.layer.read_at(path, offset, buf) {
+                Ok(Some(count)) => return Ok(count),
+                Ok(_) => {},
+                Err(e) => return Err(self.error(index, e))
+            }
+            if layer.layer.is_whiteout(path) {
+                break;
+            }
+        }
+        Err(LayerError::NotFound(path.to_path_buf()))
+    }
+
+    /// Loads the path into `buf` from the highest priority layer that provides it, returning the size of the file
+    pub fn load_into<P: AsRef<Path>>(&self, path: P, buf: &mut [u8]) -> Result<usize, LayerError> {
+        match self.layers.len() {
+            0 => Err(LayerError::NotFound(path.as_ref().to_path_buf())),
+            len => self.load_into_from(len - 1, path, buf)
+        }
+    }
+
+    /// Loads the path into `buf` starting at the layer `index` and working down the stack
+    pub fn load_into_from<P: AsRef<Path>>(&self, index: usize, path: P, buf: &mut [u8]) -> Result<usize, LayerError> {
+        let path = path.as_ref();
+        for (index, layer) in self.layers.iter().enumerate().take(index + 1).rev() {
+            match layer.layer.load_into(path, buf) {
+                Ok(Some(size)) => return Ok(size),
+                Ok(_) => {},
+                Err(LoadIntoError::BufferTooSmall { size, capacity }) => return Err(LayerError::BufferTooSmall {
+                    path: path.to_path_buf(),
+                    size,
+                    capacity
+                }),
+                Err(LoadIntoError::Loader(e)) => return Err(self.error(index, e))
+            }
+            if layer.layer.is_whiteout(path) {
+                break;
+            }
+        }
+        Err(LayerError::NotFound(path.to_path_buf()))
+    }
+
+    // The layers which can see the path, from the top of the stack down to the first whiteout
+    fn visible_layers(&self, local_path: &Path) -> std::iter::Take<std::iter::Rev<std::slice::Iter<'_, NamedLayer>>> {
+        let count = self.layers
+            .iter()
+            .rev()
+            .position(|layer| layer.layer.is_whiteout(local_path))
+            .map(|index| index + 1)
+            .unwrap_or(self.layers.len());
+        self.layers.iter().rev().take(count)
+    }
+
+    /// Gets the largest filesize reported for the path across every layer. A `Tree` layer only reports paths which are in the tree
+    pub fn query_max_filesize<P: AsRef<Path>>(&self, local_path: P) -> Option<usize> {
+        let local_path = local_path.as_ref();
+        self.visible_layers(local_path)
+            .filter_map(|layer| layer.layer.query_filesize(local_path))
+            .max()
+    }
+
+    /// Gets the filesize reported by a single layer
+    pub fn layer_filesize<P: AsRef<Path>>(&self, index: usize, local_path: P) -> Option<usize> {
+        self.layer(index)?.query_filesize(local_path.as_ref())
+    }
+
+    /// Gets the actual path from the highest priority layer that knows about the path
+    pub fn query_actual_path<P: AsRef<Path>>(&self, local_path: P) -> Option<PathBuf> {
+        let local_path = local_path.as_ref();
+        self.visible_layers(local_path)
+            .find_map(|layer| layer.layer.get_full_path(local_path))
+    }
+
+    pub fn get_entry_type<P: AsRef<Path>>(&self, index: usize, local_path: P) -> Result<FileEntryType, LayerError> {
+        let local_path = local_path.as_ref();
+        match self.layer(index) {
+            Some(layer) => layer.get_path_type(local_path).map_err(|e| self.error(index, e)),
+            None => Err(LayerError::NotFound(local_path.to_path_buf()))
+        }
+    }
+
+    /// Gets the children of the path across every layer, leaving out children hidden by a whiteout or opaque directory in a higher layer
+    pub fn get_children<P: AsRef<Path>>(&self, local_path: P) -> HashSet<&Path> {
+        let local_path = local_path.as_ref();
+        let mut children = HashSet::new();
+        for (index, layer) in self.visible_layers(local_path).enumerate() {
+            let above = &self.layers[self.layers.len() - index..];
+            children.extend(
+                layer.layer
+                    .get_children(local_path)
+                    .into_iter()
+                    .filter(|child| !above.iter().any(|upper| upper.layer.is_whiteout(child)))
+            );
+        }
+        children
+    }
+
+    pub fn contains<P: AsRef<Path>>(&self, local_path: P) -> bool {
+        let local_path = local_path.as_ref();
+        self.visible_layers(local_path).any(|layer| layer.layer.contains(local_path))
+    }
+
+    /// Walks the paths of a single layer
+    pub fn walk_layer<F: FnMut(&Node, FileEntryType)>(&self, index: usize, mut f: F) {
+        if let Some(layer) = self.layer(index) {
+            layer.walk_paths(&mut f);
+        }
+    }
+
+    /// Walks the paths of every layer, from the top of the stack down. The callback also receives the index of the layer
+    pub fn walk_layers<F: FnMut(usize, &Node, FileEntryType)>(&self, mut f: F) {
+        for (index, layer) in self.layers.iter().enumerate().rev() {
+            layer.layer.walk_paths(&mut |node, entry_type| f(index, node, entry_type));
+        }
+    }
+}
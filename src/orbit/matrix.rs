@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use super::ConflictReport;
+
+/// `ConflictMatrix` maps every pair of conflicting roots to the local paths they conflict on, regardless of which root won.
+/// See `ConflictReport::matrix`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConflictMatrix {
+    roots: Vec<PathBuf>,
+    cells: BTreeMap<(usize, usize), Vec<PathBuf>>
+}
+
+fn dot_escape(path: &Path) -> String {
+    path.display().to_string().replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl ConflictMatrix {
+    pub fn from_report(report: &ConflictReport) -> Self {
+        let pairs = report.pairs();
+        let roots: Vec<PathBuf> = pairs
+            .iter()
+            .flat_map(|pair| vec![pair.winner.clone(), pair.loser.clone()])
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let mut cells: BTreeMap<(usize, usize), BTreeSet<PathBuf>> = BTreeMap::new();
+        for pair in pairs.into_iter() {
+            let a = roots.binary_search(&pair.winner).unwrap();
+            let b = roots.binary_search(&pair.loser).unwrap();
+            cells
+                .entry((a.min(b), a.max(b)))
+                .or_default()
+                .extend(pair.entries.into_iter().map(|entry| entry.local));
+        }
+        Self {
+            roots,
+            cells: cells.into_iter().map(|(key, paths)| (key, paths.into_iter().collect())).collect()
+        }
+    }
+
+    /// Every root that is part of a conflict, sorted by path. The index of a root in this list is its row/column in the matrix
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    pub fn root_index<P: AsRef<Path>>(&self, root: P) -> Option<usize> {
+        self.roots.binary_search_by(|probe| probe.as_path().cmp(root.as_ref())).ok()
+    }
+
+    /// Gets the local paths that the roots at rows `a` and `b` conflict on, which is the same as `get(b, a)`
+    pub fn get(&self, a: usize, b: usize) -> &[PathBuf] {
+        self.cells
+            .get(&(a.min(b), a.max(b)))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Gets the local paths that two roots conflict on
+    pub fn between<P: AsRef<Path>, Q: AsRef<Path>>(&self, a: P, b: Q) -> &[PathBuf] {
+        match (self.root_index(a), self.root_index(b)) {
+            (Some(a), Some(b)) => self.get(a, b),
+            _ => &[]
+        }
+    }
+
+    /// Iterates over every pair of conflicting roots along with the local paths they conflict on
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, &Path, &[PathBuf])> {
+        self.cells
+            .iter()
+            .map(move |((a, b), paths)| (self.roots[*a].as_path(), self.roots[*b].as_path(), paths.as_slice()))
+    }
+
+    /// Exports the matrix as an undirected Graphviz DOT graph, with an edge between every pair of conflicting roots
+    /// labeled with the number of local paths they conflict on
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph conflicts {\n");
+        for root in self.roots.iter() {
+            let _ = writeln!(dot, "    \"{}\";", dot_escape(root));
+        }
+        for ((a, b), paths) in self.cells.iter() {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -- \"{}\" [label=\"{}\", weight={}];",
+                dot_escape(&self.roots[*a]),
+                dot_escape(&self.roots[*b]),
+                paths.len(),
+                paths.len()
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot_escape_test() {
+        assert!(dot_escape(Path::new("mods/plain")) == "mods/plain");
+        assert!(dot_escape(Path::new("mods/\"quoted\"")) == "mods/\\\"quoted\\\"");
+        assert!(dot_escape(Path::new("C:\\mods\\a")) == "C:\\\\mods\\\\a");
+
+        let mut cells = BTreeMap::new();
+        cells.insert((0, 1), vec![PathBuf::from("ui/param.prc")]);
+        let matrix = ConflictMatrix {
+            roots: vec!["mods/\"a\"".into(), "mods\\b".into()],
+            cells
+        };
+        assert!(matrix.to_dot() == "graph conflicts {\n    \"mods/\\\"a\\\"\";\n    \"mods\\\\b\";\n    \"mods/\\\"a\\\"\" -- \"mods\\\\b\" [label=\"1\", weight=1];\n}\n");
+    }
+}
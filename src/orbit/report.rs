@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::{ConflictKind, ConflictMatrix};
+
+/// How a conflicting path was handled
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReportKind {
+    /// The losing root's file was not served
+    Standard,
+    /// The losing root was rejected because of this path
+    RejectedRoot,
+    /// Both files were identical, so the losing root's file was accepted as a duplicate
+    Duplicate,
+    /// A file and a directory collided, see `ConflictKind::TypeConflict`
+    TypeMismatch
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReportEntry {
+    pub local: PathBuf,
+    pub kind: ReportKind
+}
+
+/// Every path that two roots conflicted on, sorted by local path
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RootPairConflicts {
+    /// The root whose files are served
+    pub winner: PathBuf,
+    /// The root whose files lost (or which was rejected)
+    pub loser: PathBuf,
+    pub entries: Vec<ReportEntry>
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConflictSummary {
+    /// The number of file conflicts, not counting type conflicts, duplicates or rejected roots
+    pub conflicts: usize,
+    pub type_conflicts: usize,
+    pub duplicates: usize,
+    pub rejected_roots: usize,
+    /// The number of distinct `(winner, loser)` root pairs
+    pub root_pairs: usize,
+    /// The number of distinct local paths involved in any conflict
+    pub paths: usize
+}
+
+/// `ConflictReport` groups the conflicts of a discovery session by root pair and local path, see `LaunchPad::conflict_report`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConflictReport {
+    pairs: BTreeMap<(PathBuf, PathBuf), BTreeMap<PathBuf, ReportKind>>
+}
+
+impl ConflictReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_conflicts(conflicts: &[ConflictKind]) -> Self {
+        let mut report = Self::new();
+        for conflict in conflicts.iter() {
+            report.add(conflict);
+        }
+        report
+    }
+
+    pub fn add(&mut self, conflict: &ConflictKind) {
+        let (winner, loser, local, kind) = match conflict {
+            ConflictKind::StandardConflict { error_root, source_root, local } => (source_root, error_root, local, ReportKind::Standard),
+            ConflictKind::TypeConflict { error_root, source_root, local, .. } => (source_root, error_root, local, ReportKind::TypeMismatch),
+            ConflictKind::RootConflict { root, existing_root, local } => (existing_root, root, local, ReportKind::RejectedRoot),
+            ConflictKind::Duplicate { root, existing_root, local } => (existing_root, root, local, ReportKind::Duplicate)
+        };
+        self.pairs
+            .entry((winner.clone(), loser.clone()))
+            .or_default()
+            .insert(local.clone(), kind);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Gets the conflicts for every root pair, sorted by the winning root and then the losing root
+    pub fn pairs(&self) -> Vec<RootPairConflicts> {
+        self.pairs
+            .iter()
+            .map(|((winner, loser), paths)| RootPairConflicts {
+                winner: winner.clone(),
+                loser: loser.clone(),
+                entries: paths
+                    .iter()
+                    .map(|(local, kind)| ReportEntry { local: local.clone(), kind: *kind })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Gets the paths that `loser` lost to `winner` on
+    pub fn paths_between<P: AsRef<Path>, Q: AsRef<Path>>(&self, winner: P, loser: Q) -> Vec<&Path> {
+        self.pairs
+            .get(&(winner.as_ref().to_path_buf(), loser.as_ref().to_path_buf()))
+            .map(|paths| paths.keys().map(PathBuf::as_path).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn summary(&self) -> ConflictSummary {
+        let mut summary = ConflictSummary {
+            root_pairs: self.pairs.len(),
+            ..Default::default()
+        };
+        let mut paths = BTreeSet::new();
+        for kinds in self.pairs.values() {
+            for (local, kind) in kinds.iter() {
+                paths.insert(local);
+                match kind {
+                    ReportKind::Standard => summary.conflicts += 1,
+                    ReportKind::RejectedRoot => summary.rejected_roots += 1,
+                    ReportKind::Duplicate => summary.duplicates += 1,
+                    ReportKind::TypeMismatch => summary.type_conflicts += 1
+                }
+            }
+        }
+        summary.paths = paths.len();
+        summary
+    }
+
+    /// Builds a root to root `ConflictMatrix` out of the report
+    pub fn matrix(&self) -> ConflictMatrix {
+        ConflictMatrix::from_report(self)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ConflictReport {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(serde::Serialize)]
+        struct Report {
+            summary: ConflictSummary,
+            pairs: Vec<RootPairConflicts>
+        }
+
+        serde::Serialize::serialize(&Report { summary: self.summary(), pairs: self.pairs() }, serializer)
+    }
+}
+
+impl fmt::Display for ConflictReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let summary = self.summary();
+        writeln!(
+            f,
+            "{} conflicts, {} type conflicts, {} duplicates and {} rejected roots across {} root pairs",
+            summary.conflicts,
+            summary.type_conflicts,
+            summary.duplicates,
+            summary.rejected_roots,
+            summary.root_pairs
+        )?;
+        for ((winner, loser), paths) in self.pairs.iter() {
+            writeln!(f, "'{}' over '{}':", winner.display(), loser.display())?;
+            for (local, kind) in paths.iter() {
+                match kind {
+                    ReportKind::Standard => writeln!(f, "    {}", local.display())?,
+                    ReportKind::RejectedRoot => writeln!(f, "    {} (rejected root)", local.display())?,
+                    ReportKind::Duplicate => writeln!(f, "    {} (duplicate)", local.display())?,
+                    ReportKind::TypeMismatch => writeln!(f, "    {} (file/directory mismatch)", local.display())?
+                }
+            }
+        }
+        Ok(())
+    }
+}
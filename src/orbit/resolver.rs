@@ -0,0 +1,61 @@
+use std::path::Path;
+
+use crate::{ConflictHandler, FileEntryType};
+
+/// One side of a file conflict found during discovery
+#[derive(Copy, Clone, Debug)]
+pub struct ConflictCandidate<'a> {
+    /// The root that the file belongs to. This is empty if the existing entry was inserted without a root
+    pub root: &'a Path,
+    /// The conflicting local path, which is the same for both candidates
+    pub local: &'a Path,
+    /// The priority of the root, see `LaunchPad::set_root_priority`
+    pub priority: i32,
+    /// Whether or not the candidate is a whiteout entry
+    pub whiteout: bool,
+    /// The type of the candidate. If the candidates have different types, replacing a directory removes everything underneath it
+    pub entry_type: FileEntryType
+}
+
+/// What `LaunchPad` should do about a file conflict
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConflictAction {
+    /// Keep the existing file, reporting the incoming root in a `ConflictKind::StandardConflict` (or `TypeConflict`)
+    KeepExisting,
+    /// Replace the existing file, reporting the existing root in a `ConflictKind::StandardConflict` (or `TypeConflict`)
+    Replace,
+    /// Reject the incoming root, rolling back everything it added to the tree
+    RejectRoot,
+    /// Stop discovery with a `DiscoveryError::Conflict`
+    Error
+}
+
+/// Decides how file conflicts are handled during discovery.
+/// `ConflictHandler` is the built-in resolver, and closures taking both candidates can be used for custom policies.
+pub trait ConflictResolver: Send {
+    fn resolve(&self, existing: &ConflictCandidate, incoming: &ConflictCandidate) -> ConflictAction;
+}
+
+impl ConflictResolver for ConflictHandler {
+    fn resolve(&self, existing: &ConflictCandidate, incoming: &ConflictCandidate) -> ConflictAction {
+        match self {
+            ConflictHandler::Strict => ConflictAction::Error,
+            ConflictHandler::NoRoot => ConflictAction::RejectRoot,
+            ConflictHandler::First => ConflictAction::KeepExisting,
+            ConflictHandler::Last => ConflictAction::Replace,
+            ConflictHandler::Priority => {
+                if incoming.priority > existing.priority || (incoming.priority == existing.priority && incoming.root < existing.root) {
+                    ConflictAction::Replace
+                } else {
+                    ConflictAction::KeepExisting
+                }
+            }
+        }
+    }
+}
+
+impl<F> ConflictResolver for F where F: Fn(&ConflictCandidate, &ConflictCandidate) -> ConflictAction + Send {
+    fn resolve(&self, existing: &ConflictCandidate, incoming: &ConflictCandidate) -> ConflictAction {
+        self(existing, incoming)
+    }
+}
@@ -0,0 +1,191 @@
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use std::path::Path;
+
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RuleError {
+    #[error("Invalid glob '{glob}'! Error: {error}")]
+    InvalidGlob {
+        glob: String,
+        #[source]
+        error: globset::Error
+    }
+}
+
+// The serialized form of a `RuleSet`, which is compiled when it is deserialized
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RawRuleSet {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    case_insensitive: bool
+}
+
+/// `RuleSet` matches local paths against glob patterns, and can be used with `LaunchPad::ignoring_rules` and `LaunchPad::collecting_rules`.
+///
+/// A path matches if it matches any include glob and does not match any exclude glob, so exclusions always win.
+/// A rule set without include globs matches nothing, which keeps an empty (or exclude-only) rule set from ignoring or collecting a whole root.
+/// `*` and `?` never match a path separator, while `**` matches any number of directories.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RawRuleSet", into = "RawRuleSet"))]
+pub struct RuleSet {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    case_insensitive: bool,
+    include_set: GlobSet,
+    exclude_set: GlobSet
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RuleSet {
+    /// Creates an empty, case sensitive rule set. An empty rule set matches nothing
+    pub fn new() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            case_insensitive: false,
+            include_set: GlobSet::empty(),
+            exclude_set: GlobSet::empty()
+        }
+    }
+
+    fn compile(globs: &[String], case_insensitive: bool) -> Result<GlobSet, RuleError> {
+        let mut builder = GlobSetBuilder::new();
+        for glob in globs.iter() {
+            builder.add(Self::glob(glob, case_insensitive)?);
+        }
+        builder.build().map_err(|error| RuleError::InvalidGlob { glob: globs.join(", "), error })
+    }
+
+    fn glob(glob: &str, case_insensitive: bool) -> Result<Glob, RuleError> {
+        GlobBuilder::new(glob)
+            .literal_separator(true)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|error| RuleError::InvalidGlob { glob: glob.to_string(), error })
+    }
+
+    fn rebuild(&mut self) -> Result<(), RuleError> {
+        self.include_set = Self::compile(&self.include, self.case_insensitive)?;
+        self.exclude_set = Self::compile(&self.exclude, self.case_insensitive)?;
+        Ok(())
+    }
+
+    /// Adds a glob which paths must match. The rule set is left unchanged if the glob is invalid
+    pub fn include<S: Into<String>>(&mut self, glob: S) -> Result<&mut Self, RuleError> {
+        let mut include = self.include.clone();
+        include.push(glob.into());
+        self.include_set = Self::compile(&include, self.case_insensitive)?;
+        self.include = include;
+        Ok(self)
+    }
+
+    /// Adds a glob which paths must not match. The rule set is left unchanged if the glob is invalid
+    pub fn exclude<S: Into<String>>(&mut self, glob: S) -> Result<&mut Self, RuleError> {
+        let mut exclude = self.exclude.clone();
+        exclude.push(glob.into());
+        self.exclude_set = Self::compile(&exclude, self.case_insensitive)?;
+        self.exclude = exclude;
+        Ok(self)
+    }
+
+    /// Sets whether or not globs ignore ASCII case
+    pub fn case_insensitive(&mut self, case_insensitive: bool) -> &mut Self {
+        self.case_insensitive = case_insensitive;
+        // Every glob was already validated when it was added, and case sensitivity can't make one invalid
+        self.rebuild().expect("Failed to recompile valid globs!");
+        self
+    }
+
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    pub fn includes(&self) -> &[String] {
+        &self.include
+    }
+
+    pub fn excludes(&self) -> &[String] {
+        &self.exclude
+    }
+
+    pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        self.include_set.is_match(path) && !self.exclude_set.is_match(path)
+    }
+}
+
+impl PartialEq for RuleSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.include == other.include && self.exclude == other.exclude && self.case_insensitive == other.case_insensitive
+    }
+}
+
+impl Eq for RuleSet {}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawRuleSet> for RuleSet {
+    type Error = RuleError;
+
+    fn try_from(raw: RawRuleSet) -> Result<Self, Self::Error> {
+        let mut rules = Self {
+            include: raw.include,
+            exclude: raw.exclude,
+            case_insensitive: raw.case_insensitive,
+            ..Self::new()
+        };
+        rules.rebuild()?;
+        Ok(rules)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<RuleSet> for RawRuleSet {
+    fn from(rules: RuleSet) -> Self {
+        Self {
+            include: rules.include,
+            exclude: rules.exclude,
+            case_insensitive: rules.case_insensitive
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence_test() {
+        let mut rules = RuleSet::new();
+        assert!(!rules.is_match("ui/param.prc"));
+        rules.exclude("ui/**").unwrap();
+        assert!(!rules.is_match("ui/param.prc") && !rules.is_match("fighter/model.numdlb"));
+
+        // Exclusions win over inclusions no matter which was added first
+        rules.include("**/*.prc").unwrap();
+        rules.include("fighter/**").unwrap();
+        rules.exclude("fighter/c00/**").unwrap();
+        assert!(!rules.is_match("ui/param.prc"));
+        assert!(rules.is_match("stage/param.prc"));
+        assert!(rules.is_match("fighter/c01/model.numdlb"));
+        assert!(!rules.is_match("fighter/c00/model.numdlb"));
+        assert!(!rules.is_match("fighter/c00/param.prc"));
+
+        // `*` stays within a directory, unlike `**`
+        let mut rules = RuleSet::new();
+        rules.include("ui/*.prc").unwrap();
+        assert!(rules.is_match("ui/param.prc") && !rules.is_match("ui/sub/param.prc"));
+    }
+}
@@ -7,7 +7,7 @@ use std::io;
 
 use thiserror::Error;
 
-use crate::{FileEntryType, loader::FileLoader};
+use crate::{FileEntryType, loader::{FileLoader, LoadIntoError, ReadSeek}};
 
 pub mod node;
 pub mod loader;
@@ -32,6 +32,10 @@ pub enum TreeError {
     OwnedPath(PathBuf),
     #[error("Failed to add child '{0}' to node at '{1}' despite it not existing!")]
     PhantomNode(String, PathBuf),
+    #[error("The path '{0}' is not inside of its mount path '{1}'!")]
+    OutsideMount(PathBuf, PathBuf),
+    #[error("Aliasing '{0}' to '{1}' would create an alias cycle!")]
+    AliasCycle(PathBuf, PathBuf),
 }
 
 
@@ -59,25 +63,23 @@ impl<T: TreeNode> RawNode<T> where <T as TreeNode>::TreeKey: Hash + Eq + Clone {
         }
     }
 
-    pub fn children(&self) -> std::collections::hash_map::Iter<T::TreeKey, RawNode<T>> {
+    pub fn children(&self) -> std::collections::hash_map::Iter<'_, T::TreeKey, RawNode<T>> {
         self.children.iter()
     }
 
-    pub fn children_mut(&mut self) -> std::collections::hash_map::IterMut<T::TreeKey, RawNode<T>> {
+    pub fn children_mut(&mut self) -> std::collections::hash_map::IterMut<'_, T::TreeKey, RawNode<T>> {
         self.children.iter_mut()
     }
 
-    pub fn get_child<A: ?Sized>(&self, key: &A) -> Option<&Self>
+    pub fn get_child<A: ?Sized + Hash + Eq>(&self, key: &A) -> Option<&Self>
     where
-        <T as TreeNode>::TreeKey: Borrow<A>,
-        A: Hash + Eq {
+        <T as TreeNode>::TreeKey: Borrow<A> {
         self.children.get(key)
     }
 
-    pub fn get_child_mut<A: ?Sized>(&mut self, key: &A) -> Option<&mut Self>
+    pub fn get_child_mut<A: ?Sized + Hash + Eq>(&mut self, key: &A) -> Option<&mut Self>
     where
-        <T as TreeNode>::TreeKey: Borrow<A>,
-        A: Hash + Eq {
+        <T as TreeNode>::TreeKey: Borrow<A> {
         self.children.get_mut(key)
     }
 
@@ -104,18 +106,116 @@ impl<T: TreeNode> Hash for RawNode<T> where <T as TreeNode>::TreeKey: Hash {
     }
 }
 
+/// A root which can provide a file in the tree. Files keep every candidate they were given, in priority order,
+/// and the active candidate is the one that the tree serves
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub root: PathBuf,
+    pub whiteout: bool,
+    /// The local path that the root is mounted at, see `Node::get_mount`
+    pub mount: PathBuf
+}
+
+/// A local path which serves the data of another local path, see `Tree::insert_alias`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alias {
+    pub target: PathBuf
+}
+
+// Where the data for a local path comes from once the aliases inside of the tree have been followed
+enum Lookup<'a> {
+    Node(&'a RawNode<RawTreeNode>),
+    // The path is not in the tree, so it is handed to the loader without a root
+    Loader(&'a Path),
+    // The path is an alias which leads nowhere, so there is nothing to hand to the loader
+    Dangling(&'a Path)
+}
+
+// An entry that has been detached from a tree, along with its children
+pub(crate) struct Subtree {
+    parent: PathBuf,
+    node: RawNode<RawTreeNode>
+}
+
 struct RawTreeNode {
     raw: Node,
-    entry_type: FileEntryType
+    entry_type: FileEntryType,
+    whiteout: bool,
+    // The roots which marked the directory as opaque. Marks made with `Tree::set_opaque` belong to the empty root
+    opaque: Vec<PathBuf>,
+    // The roots which marked the directory with `Tree::add_opaque_root`. Entries underneath it from any other root are hidden
+    markers: Vec<PathBuf>,
+    // The roots which provide a directory or anything underneath it, in the order that they were added
+    roots: Vec<PathBuf>,
+    candidates: Vec<Candidate>,
+    active: usize,
+    alias: Option<Alias>
 }
 
 impl RawTreeNode {
     pub fn new(raw: Node, entry_type: FileEntryType) -> Self {
+        let candidates = match entry_type {
+            FileEntryType::File => vec![Candidate { root: raw.root_path.clone(), whiteout: false, mount: raw.mount_path.clone() }],
+            FileEntryType::Directory => Vec::new()
+        };
+        Self {
+            raw,
+            entry_type,
+            whiteout: false,
+            opaque: Vec::new(),
+            markers: Vec::new(),
+            roots: Vec::new(),
+            candidates,
+            active: 0,
+            alias: None
+        }
+    }
+
+    pub fn whiteout(raw: Node) -> Self {
+        Self {
+            candidates: vec![Candidate { root: raw.root_path.clone(), whiteout: true, mount: raw.mount_path.clone() }],
+            raw,
+            entry_type: FileEntryType::File,
+            whiteout: true,
+            opaque: Vec::new(),
+            markers: Vec::new(),
+            roots: Vec::new(),
+            active: 0,
+            alias: None
+        }
+    }
+
+    pub fn alias(raw: Node, alias: Alias) -> Self {
         Self {
             raw,
-            entry_type
+            entry_type: FileEntryType::File,
+            whiteout: false,
+            opaque: Vec::new(),
+            markers: Vec::new(),
+            roots: Vec::new(),
+            candidates: Vec::new(),
+            active: 0,
+            alias: Some(alias)
         }
     }
+
+    // Checks if the entry comes from one of the roots which marked each directory above it, see `Tree::add_opaque_root`.
+    // Entries without a root (aliases and directories which were inserted by hand) are never hidden
+    fn is_visible(&self, markers: &[&[PathBuf]]) -> bool {
+        let roots = match self.entry_type {
+            FileEntryType::Directory => self.roots.as_slice(),
+            FileEntryType::File => std::slice::from_ref(&self.raw.root_path)
+        };
+        roots.iter().all(|root| root.as_os_str().is_empty())
+            || markers.iter().all(|markers| roots.iter().any(|root| markers.contains(root)))
+    }
+
+    fn activate(&mut self, index: usize) {
+        self.active = index;
+        self.raw.root_path = self.candidates[index].root.clone();
+        self.raw.mount_path = self.candidates[index].mount.clone();
+        self.whiteout = self.candidates[index].whiteout;
+    }
 }
 
 impl TreeNode for RawTreeNode {
@@ -129,11 +229,59 @@ impl TreeNode for RawTreeNode {
 
 pub struct Tree<L: FileLoader> {
     pub loader: L,
-    root: RawNode<RawTreeNode>
+    root: RawNode<RawTreeNode>,
+    // Bumped by every mutable access to the nodes, see `Tree::generation`
+    generation: u64
 }
 
 impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
+    // Whiteouts are only visible through `get_raw_path`, every other lookup treats them as absent
     fn get_path(&self, path: &Path) -> Option<&RawNode<RawTreeNode>> {
+        self.get_raw_path(path).filter(|node| !node.data.whiteout)
+    }
+
+    // Gets the path like `get_path`, unless it is hidden by an opaque directory above it.
+    // The markers of every directory on the way (including the entry itself) are returned alongside it
+    fn get_visible_path(&self, path: &Path) -> Option<(&RawNode<RawTreeNode>, Vec<&[PathBuf]>)> {
+        let mut current_node = &self.root;
+        let mut markers = Vec::new();
+        for key in path
+            .components()
+            .map(|x| x.as_os_str().to_str().unwrap()) {
+            if !current_node.data.markers.is_empty() {
+                markers.push(current_node.data.markers.as_slice());
+            }
+            current_node = current_node.get_child(key)?;
+        }
+        if current_node.data.whiteout || !current_node.data.is_visible(&markers) {
+            return None;
+        }
+        if !current_node.data.markers.is_empty() {
+            markers.push(current_node.data.markers.as_slice());
+        }
+        Some((current_node, markers))
+    }
+
+    // Follows the aliases inside of the tree, starting at the local path.
+    // An alias whose target is not in the tree, or which is part of a cycle (which `Tree::insert_alias` refuses to create), is dangling
+    fn lookup<'a>(&'a self, path: &'a Path) -> Lookup<'a> {
+        let mut current = path;
+        let mut visited = HashSet::new();
+        loop {
+            let node = match self.get_visible_path(current) {
+                Some((node, _)) => node,
+                None if visited.is_empty() => return Lookup::Loader(path),
+                None => return Lookup::Dangling(path)
+            };
+            match &node.data.alias {
+                None => return Lookup::Node(node),
+                Some(Alias { target }) if visited.insert(current) => current = target,
+                Some(_) => return Lookup::Dangling(path)
+            }
+        }
+    }
+
+    fn get_raw_path(&self, path: &Path) -> Option<&RawNode<RawTreeNode>> {
         let mut current_node = Some(&self.root);
 
         for key in path
@@ -152,6 +300,7 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
     }
 
     fn get_path_mut(&mut self, path: &Path) -> Option<&mut RawNode<RawTreeNode>> {
+        self.generation += 1;
         let mut current_node = Some(&mut self.root);
 
         for key in path
@@ -173,38 +322,118 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
     pub fn new(loader: L) -> Self {
         Self {
             root: RawNode::new(RawTreeNode::new(Node::root(), FileEntryType::Directory)),
-            loader
+            loader,
+            generation: 0
         }
     }
 
+    /// A counter which changes whenever the tree (or its loader, through `Tree::loader_mut`) might have been modified.
+    /// `Orbit` uses it to notice modifications that it wasn't told about
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
     /// Attempts to load the specified local path with the loader. If the path is not contained inside of the tree, then `Ok(None)` is returned.
     /// The loader is responsible for returning valid data. If it can't load valid data, it is expected to return an `Err(L::ErrorType)`
     pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<Option<Vec<u8>>, L::ErrorType> {
-        let path = path.as_ref();
-        if let Some(node) = self.get_path(path) {
-            Ok(Some(self.loader.load_path(&node.data.raw.root_path, &node.data.raw.local_path)?))
-        } else {
-            //println!("get_path none: {}", path.display());
-            match self.loader.load_path(Path::new(""), path) {
+        match self.lookup(path.as_ref()) {
+            Lookup::Node(node) => Ok(Some(self.loader.load_path(&node.data.raw.root_path, node.data.raw.get_source_local())?)),
+            Lookup::Loader(path) => match self.loader.load_path(Path::new(""), path) {
                 Ok(data) => Ok(Some(data)),
                 Err(_) => Ok(None),
-            }
+            },
+            Lookup::Dangling(_) => Ok(None)
         }
     }
 
-    /// Checks the filesystem to see if a file exists
+    /// Attempts to open the specified local path for streaming with the loader. If the path is not contained inside of the tree, then `Ok(None)` is returned.
+    /// This follows the same rules as `Tree::load`
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<Option<Box<dyn ReadSeek + '_>>, L::ErrorType> {
+        match self.lookup(path.as_ref()) {
+            Lookup::Node(node) => Ok(Some(self.loader.open(&node.data.raw.root_path, node.data.raw.get_source_local())?)),
+            Lookup::Loader(path) => match self.loader.open(Path::new(""), path) {
+                Ok(reader) => Ok(Some(reader)),
+                Err(_) => Ok(None),
+            },
+            Lookup::Dangling(_) => Ok(None)
+        }
+    }
+
+    /// Attempts to read part of the specified local path into `buf`, starting at `offset`. If the path is not contained inside of the tree, then `Ok(None)` is returned.
+    /// This follows the same rules as `Tree::load`
+    pub fn read_at<P: AsRef<Path>>(&self, path: P, offset: u64, buf: &mut [u8]) -> Result<Option<usize>, L::ErrorType> {
+        match self.lookup(path.as_ref()) {
+            Lookup::Node(node) => Ok(Some(self.loader.read_at(&node.data.raw.root_path, node.data.raw.get_source_local(), offset, buf)?)),
+            Lookup::Loader(path) => match self.loader.read_at(Path::new(""), path, offset, buf) {
+                Ok(count) => Ok(Some(count)),
+                Err(_) => Ok(None),
+            },
+            Lookup::Dangling(_) => Ok(None)
+        }
+    }
+
+    /// Attempts to load the specified local path into `buf`, returning the size of the file. If the path is not contained inside of the tree, then `Ok(None)` is returned.
+    /// This follows the same rules as `Tree::load`, except that a file which does not fit into `buf` is always reported as an error
+    pub fn load_into<P: AsRef<Path>>(&self, path: P, buf: &mut [u8]) -> Result<Option<usize>, LoadIntoError<L::ErrorType>> {
+        match self.lookup(path.as_ref()) {
+            Lookup::Node(node) => Ok(Some(self.loader.load_into(&node.data.raw.root_path, node.data.raw.get_source_local(), buf)?)),
+            Lookup::Loader(path) => match self.loader.load_into(Path::new(""), path, buf) {
+                Ok(size) => Ok(Some(size)),
+                Err(LoadIntoError::Loader(_)) => Ok(None),
+                Err(e) => Err(e)
+            },
+            Lookup::Dangling(_) => Ok(None)
+        }
+    }
+
+    /// Gets the root path and local path that the loader is given when loading the specified local path, following the same rules as `Tree::load`.
+    /// If the path is not contained inside of the tree and the loader can't find it either, `None` is returned
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> Option<(PathBuf, PathBuf)> {
+        match self.lookup(path.as_ref()) {
+            Lookup::Node(node) => Some((node.data.raw.root_path.clone(), node.data.raw.get_source_local().to_path_buf())),
+            Lookup::Loader(path) if self.loader.path_exists(Path::new(""), path) => Some((PathBuf::new(), path.to_path_buf())),
+            _ => None
+        }
+    }
+
+    /// Checks the filesystem to see if a file exists. Entries hidden by an opaque directory (see `Tree::add_opaque_root`) don't count
     pub fn contains_path<P: AsRef<Path>>(&self, path: P) -> bool {
-        self.get_path(path.as_ref()).is_some()
+        self.get_visible_path(path.as_ref()).is_some()
+    }
+
+    // Checks if there is an entry (other than a whiteout) at the path, even if it is hidden by an opaque directory
+    pub(crate) fn contains_entry(&self, path: &Path) -> bool {
+        self.get_path(path).is_some()
     }
 
     fn insert_path_unchecked(&mut self, root_path: &Path, local_path: &Path, entry_type: FileEntryType) -> Option<(PathBuf, PathBuf)> {
+        let node = match entry_type {
+            FileEntryType::Directory => {
+                let mut node = RawTreeNode::new(Node::new(Path::new(""), local_path).unwrap(), entry_type);
+                node.roots.push(root_path.to_path_buf());
+                node
+            },
+            FileEntryType::File => RawTreeNode::new(Node::new(root_path, local_path).unwrap(), entry_type)
+        };
+        self.insert_node_unchecked(local_path, node)
+    }
+
+    fn insert_node_unchecked(&mut self, local_path: &Path, mut node: RawTreeNode) -> Option<(PathBuf, PathBuf)> {
+        self.generation += 1;
+        node.roots.retain(|root| !root.as_os_str().is_empty());
+        let contributor = match node.entry_type {
+            FileEntryType::Directory => node.roots.first().cloned(),
+            FileEntryType::File => Some(node.raw.root_path.clone()).filter(|root| !root.as_os_str().is_empty())
+        };
         let parent_node = if let Some(parent_path) = local_path.parent() {
             if parent_path == Path::new("/") || parent_path == Path::new("") {
                 &mut self.root
             } else if let Some(parent) = self.get_path_mut(parent_path) {
                 parent
             } else {
-                assert!(self.insert_path_unchecked(&Path::new(""), parent_path, FileEntryType::Directory).is_none());
+                if let Some(blocked) = self.insert_path_unchecked(Path::new(""), parent_path, FileEntryType::Directory) {
+                    return Some(blocked);
+                }
                 match self.get_path_mut(parent_path) {
                     Some(node) => node,
                     None => panic!("Failed to find parent node '{}' immediately after adding it", parent_path.display())
@@ -214,30 +443,219 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
             &mut self.root 
         };
 
-        let node = match entry_type {
-            FileEntryType::Directory => Node::new(Path::new(""), local_path).unwrap(),
-            FileEntryType::File => Node::new(root_path, local_path).unwrap()
-        };
+        // A whiteout can't have children, so it becomes an opaque directory which keeps hiding everything else in the layers below it,
+        // owned by every root which whited the path out. A whiteout which can fall back to a file (see `Tree::candidates`) blocks the insertion instead
+        if parent_node.data.whiteout {
+            if parent_node.data.candidates.iter().any(|candidate| !candidate.whiteout) {
+                return Some((parent_node.data.raw.root_path.clone(), parent_node.data.raw.local_path.clone()));
+            }
+            let parent_path = local_path.parent().expect("The root node can't be a whiteout");
+            let owners: Vec<PathBuf> = parent_node.data.candidates.iter().map(|candidate| candidate.root.clone()).collect();
+            parent_node.data = RawTreeNode::new(Node::new(Path::new(""), parent_path).unwrap(), FileEntryType::Directory);
+            parent_node.data.roots = owners.iter().filter(|root| !root.as_os_str().is_empty()).cloned().collect();
+            parent_node.data.opaque = owners;
+        }
 
-        if let Some(RawTreeNode{ raw: Node { local_path: local, root_path: root, .. }, .. }) = parent_node.add_child(RawTreeNode::new(node, entry_type), true) {
+        let replaced = parent_node.add_child(node, true);
+
+        // Every directory above the new entry is now provided by its root as well
+        if let Some(root) = contributor {
+            let mut current_node = &mut self.root;
+            for key in local_path
+                .parent()
+                .into_iter()
+                .flat_map(Path::components)
+                .map(|x| x.as_os_str().to_str().unwrap()) {
+                current_node = match current_node.get_child_mut(key) {
+                    Some(node) => node,
+                    None => break
+                };
+                if !current_node.data.roots.contains(&root) {
+                    current_node.data.roots.push(root.clone());
+                }
+            }
+        }
+
+        if let Some(RawTreeNode{ raw: Node { local_path: local, root_path: root, .. }, .. }) = replaced {
             Some((root, local))
         } else {
             None
         }
     }
 
-    /// Inserts a file into the file tree.
+    /// Inserts a file into the file tree, returning the root path and local path of the entry it replaced.
     /// This operation is unchecked, and the loader does not confirm that this file exists when adding it to the file tree.
+    /// If a whiteout which can fall back to a file is in the way (see `Tree::insert_whiteout`), nothing is inserted and the whiteout is returned instead
     pub fn insert_file<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, root_path: P, local_path: Q) -> Option<(PathBuf, PathBuf)> {
         self.insert_path_unchecked(root_path.as_ref(), local_path.as_ref(), FileEntryType::File)
     }
 
+    /// Inserts a file from a root which is mounted at `mount_path` in the file tree, see `Node::new_mounted`.
+    /// The loader is given the local path without the mount path. Returns `Err` if the local path is not inside of the mount path
+    pub fn insert_mounted_file<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(&mut self, root_path: P, mount_path: Q, local_path: R) -> Result<Option<(PathBuf, PathBuf)>, TreeError> {
+        let local_path = local_path.as_ref();
+        let node = Node::new_mounted(root_path, mount_path, local_path)?;
+        Ok(self.insert_node_unchecked(local_path, RawTreeNode::new(node, FileEntryType::File)))
+    }
+
+    /// Inserts a whiteout from a root which is mounted at `mount_path` in the file tree, see `Tree::insert_whiteout` and `Tree::insert_mounted_file`
+    pub fn insert_mounted_whiteout<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(&mut self, root_path: P, mount_path: Q, local_path: R) -> Result<Option<(PathBuf, PathBuf)>, TreeError> {
+        let local_path = local_path.as_ref();
+        let node = Node::new_mounted(root_path, mount_path, local_path)?;
+        Ok(self.insert_node_unchecked(local_path, RawTreeNode::whiteout(node)))
+    }
+
     /// Inserts a directory into the file tree.
     /// This operation is unchecked, and the loader does not confirm that this file exists when adding it to the file tree.
     pub fn insert_directory<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, root_path: P, local_path: Q) -> Option<(PathBuf, PathBuf)> {
         self.insert_path_unchecked(root_path.as_ref(), local_path.as_ref(), FileEntryType::Directory)
     }
 
+    /// Inserts a whiteout into the file tree. A whiteout marks the local path as removed: the tree reports it as absent,
+    /// and an `Orbit` will not consult the layers below this tree for it (or for anything underneath it).
+    /// Inserting an entry underneath a whiteout turns the whiteout into an opaque directory (see `Tree::set_opaque`), unless one of its candidates
+    /// is a file that it would fall back to when its root is removed. That file can't coexist with the new entry, so the insertion is refused
+    pub fn insert_whiteout<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, root_path: P, local_path: Q) -> Option<(PathBuf, PathBuf)> {
+        let local_path = local_path.as_ref();
+        let node = Node::new(root_path, local_path).unwrap();
+        self.insert_node_unchecked(local_path, RawTreeNode::whiteout(node))
+    }
+
+    /// Inserts an alias, which makes the local path serve whatever the tree serves for `target` (without copying anything).
+    /// Aliases are followed by `Tree::load`, `Tree::query_filesize`, `Tree::get_full_path` and the like, and can point at other aliases.
+    /// An alias whose target is not in the tree is dangling, and is treated as not found. Returns `Err` if the alias would create a cycle
+    pub fn insert_alias<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, local_path: P, target: Q) -> Result<Option<(PathBuf, PathBuf)>, TreeError> {
+        let local_path = local_path.as_ref();
+        let target = target.as_ref();
+        let mut current = target;
+        loop {
+            if current == local_path {
+                return Err(TreeError::AliasCycle(local_path.to_path_buf(), target.to_path_buf()));
+            }
+            match self.get_alias(current) {
+                Some(alias) => current = &alias.target,
+                None => break
+            }
+        }
+        let node = Node::new(Path::new(""), local_path)?;
+        Ok(self.insert_node_unchecked(local_path, RawTreeNode::alias(node, Alias { target: target.to_path_buf() })))
+    }
+
+    /// Gets the alias at exactly the local path, without following it
+    pub fn get_alias<P: AsRef<Path>>(&self, path: P) -> Option<&Alias> {
+        self.get_path(path.as_ref()).and_then(|node| node.data.alias.as_ref())
+    }
+
+    /// Checks if the local path, or one of its parents, has been removed by a whiteout
+    pub fn is_whiteout<P: AsRef<Path>>(&self, path: P) -> bool {
+        let mut current_node = &self.root;
+        for key in path
+            .as_ref()
+            .components()
+            .map(|x| x.as_os_str().to_str().unwrap()) {
+            match current_node.get_child(key) {
+                Some(node) if node.data.whiteout => return true,
+                Some(node) => current_node = node,
+                None => return false
+            }
+        }
+        false
+    }
+
+    fn get_directory_mut(&mut self, path: &Path) -> Option<&mut RawTreeNode> {
+        self.get_path_mut(path)
+            .map(|node| &mut node.data)
+            .filter(|node| node.entry_type == FileEntryType::Directory && !node.whiteout)
+    }
+
+    /// Marks a directory as opaque, or clears every mark (including the ones made by roots, see `Tree::add_opaque_root`).
+    /// An opaque directory replaces the directory in the layers below this tree instead of merging with it,
+    /// so an `Orbit` will not consult those layers for anything underneath it. Returns `false` if there is no such directory
+    pub fn set_opaque<P: AsRef<Path>>(&mut self, path: P, opaque: bool) -> bool {
+        if opaque {
+            return self.add_opaque_root(path, "");
+        }
+        match self.get_directory_mut(path.as_ref()) {
+            Some(node) => {
+                node.opaque.clear();
+                node.markers.clear();
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Marks a directory as opaque on behalf of a root. The directory stays opaque until every root which marked it is removed,
+    /// either with `Tree::remove_opaque_root` or `Tree::remove_paths_by_root`. Returns `false` if there is no such directory.
+    /// Until then, the entries underneath the directory which come from other roots are hidden as well, since the directory replaces theirs
+    pub fn add_opaque_root<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, path: P, root: Q) -> bool {
+        let root = root.as_ref();
+        match self.get_directory_mut(path.as_ref()) {
+            Some(node) => {
+                if !node.opaque.iter().any(|owner| owner == root) {
+                    node.opaque.push(root.to_path_buf());
+                }
+                if !root.as_os_str().is_empty() && !node.markers.iter().any(|owner| owner == root) {
+                    node.markers.push(root.to_path_buf());
+                }
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Removes the mark a root made on an opaque directory. Returns `false` if the root did not mark the directory
+    pub fn remove_opaque_root<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, path: P, root: Q) -> bool {
+        let root = root.as_ref();
+        match self.get_directory_mut(path.as_ref()) {
+            Some(node) => {
+                let count = node.opaque.len();
+                node.opaque.retain(|owner| owner != root);
+                node.markers.retain(|owner| owner != root);
+                node.opaque.len() != count
+            },
+            None => false
+        }
+    }
+
+    /// Gets every root which marked the directory at the local path as opaque
+    pub fn opaque_roots<P: AsRef<Path>>(&self, path: P) -> &[PathBuf] {
+        match self.get_path(path.as_ref()) {
+            Some(node) => &node.data.opaque,
+            None => &[]
+        }
+    }
+
+    /// Checks if the directory at exactly the local path is opaque
+    pub fn is_opaque<P: AsRef<Path>>(&self, path: P) -> bool {
+        !self.opaque_roots(path).is_empty()
+    }
+
+    /// Checks if the layers below this tree are hidden for the local path, either by a whiteout on the path (or one of its parents),
+    /// or because one of its parents is an opaque directory
+    pub fn is_masked<P: AsRef<Path>>(&self, path: P) -> bool {
+        let mut current_node = &self.root;
+        for key in path
+            .as_ref()
+            .components()
+            .map(|x| x.as_os_str().to_str().unwrap()) {
+            if !current_node.data.opaque.is_empty() {
+                return true;
+            }
+            match current_node.get_child(key) {
+                Some(node) if node.data.whiteout => return true,
+                Some(node) => current_node = node,
+                None => return false
+            }
+        }
+        false
+    }
+
+    /// Checks if there is a whiteout entry at exactly the local path
+    pub fn contains_whiteout<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.get_raw_path(path.as_ref()).map(|node| node.data.whiteout).unwrap_or(false)
+    }
+
     /// Inserts a path into the file tree. If a previous entry existed, it gets replaced and the root/local path is returned.
     /// If you use `insert_path`, it is required that the path "exists" such that the `FileLoader` can get it's entry type
     pub fn insert_path<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, root_path: P, local_path: Q) -> Option<(PathBuf, PathBuf)> {
@@ -250,6 +668,12 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
     /// Removes a path from the file tree. If the entry existed, this function returns the root path and the local path separately, else
     /// it returns `None`
     pub fn remove_path<P: AsRef<Path>>(&mut self, path: P) -> Option<(PathBuf, PathBuf)> {
+        self.take_path(path).map(|Subtree { node, .. }| (node.data.raw.root_path, node.data.raw.local_path))
+    }
+
+    // Detaches the entry at the path, along with everything underneath it, so that it can be put back with `restore_path`
+    pub(crate) fn take_path<P: AsRef<Path>>(&mut self, path: P) -> Option<Subtree> {
+        self.generation += 1;
         let path = path.as_ref();
         let name = path
             .file_name()
@@ -259,48 +683,141 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
         let parent_node = if let Some(parent_path) = path.parent() {
             if parent_path == Path::new("/") || parent_path == Path::new("") {
                 &mut self.root
-            } else if let Some(parent) = self.get_path_mut(parent_path) {
-                parent
             } else {
-                return None;
+                self.get_path_mut(parent_path)?
             }
         } else {
             &mut self.root
         };
 
-        if let Some(RawNode { data: RawTreeNode { raw: Node { local_path: local, root_path: root, .. }, .. }, .. }) = parent_node.children.remove(name) {
-            Some((root, local))
+        parent_node.children.remove(name).map(|node| Subtree {
+            parent: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            node
+        })
+    }
+
+    pub(crate) fn restore_path(&mut self, subtree: Subtree) {
+        self.generation += 1;
+        let Subtree { parent, node } = subtree;
+        let parent_node = if parent.as_os_str().is_empty() || parent == Path::new("/") {
+            &mut self.root
         } else {
-            None
-        }
+            if self.get_raw_path(&parent).is_none() {
+                self.insert_path_unchecked(Path::new(""), &parent, FileEntryType::Directory);
+            }
+            self.get_path_mut(&parent).expect("Failed to find parent node immediately after adding it")
+        };
+        parent_node.children.insert(node.key.clone(), node);
     }
 
-    /// Removes all paths from the file tree who's root path is the same as the specified path. This returns a vector of all local paths that were removed
+    /// Removes all paths (including whiteouts) from the file tree who's root path is the same as the specified path. This returns a vector of all local paths that were removed.
+    /// Files which have other candidates are kept and fall back to the highest priority candidate that remains, see `Tree::candidates`.
+    /// Directories which the root marked as opaque are no longer opaque because of it
     pub fn remove_paths_by_root<P: AsRef<Path>>(&mut self, root: P) -> Vec<PathBuf> {
         let remove = root.as_ref();
+        self.forget_root(remove);
         let mut to_remove = Vec::new();
-        self.walk_paths(|node, _| {
-            if node.root_path == remove {
-                to_remove.push(node.local_path.clone());
+        self.walk_raw(|node| {
+            if node.data.raw.root_path == remove || node.data.candidates.iter().any(|candidate| candidate.root == remove) {
+                to_remove.push(node.data.raw.local_path.clone());
             }
         });
         to_remove
             .into_iter()
             .filter_map(|local_path| {
-                if let Some((_, local)) = self.remove_path(&local_path) {
-                    Some(local)
+                let node = &mut self.get_path_mut(&local_path)?.data;
+                let active_removed = node.raw.root_path == remove;
+                node.candidates.retain(|candidate| candidate.root != remove);
+                if node.candidates.is_empty() {
+                    self.remove_path(&local_path).map(|(_, local)| local)
                 } else {
+                    if active_removed {
+                        node.activate(0);
+                    } else {
+                        let active = node.raw.root_path.clone();
+                        node.active = node.candidates.iter().position(|candidate| candidate.root == active).unwrap_or(0);
+                    }
                     None
                 }
             })
             .collect()
     }
 
-    /// Recursively walk through the file tree.
-    pub fn walk_paths<F: FnMut(&Node, FileEntryType)>(&self, mut f: F) {
-        fn internal<F: FnMut(&Node, FileEntryType)>(node: &RawNode<RawTreeNode>, f: &mut F, depth: usize) {
+    // Clears every opaque mark that the root made and stops counting it as a root of any directory, see `Tree::directory_roots`
+    pub(crate) fn forget_root(&mut self, root: &Path) {
+        fn forget(node: &mut RawNode<RawTreeNode>, root: &Path) {
+            node.data.opaque.retain(|owner| owner != root);
+            node.data.markers.retain(|owner| owner != root);
+            node.data.roots.retain(|owner| owner != root);
+            for (_, child) in node.children_mut() {
+                forget(child, root);
+            }
+        }
+        self.generation += 1;
+        forget(&mut self.root, root);
+    }
+
+    /// Gets every root which provides the directory at the local path, either by adding the directory itself or an entry underneath it,
+    /// in the order that they were added. Files have no directory roots
+    pub fn directory_roots<P: AsRef<Path>>(&self, path: P) -> &[PathBuf] {
+        match self.get_path(path.as_ref()) {
+            Some(node) if node.data.entry_type == FileEntryType::Directory => &node.data.roots,
+            _ => &[]
+        }
+    }
+
+    /// Gets the entry type that the local path was inserted with, without asking the loader. Whiteouts are treated as absent
+    pub fn get_entry_type<P: AsRef<Path>>(&self, path: P) -> Option<FileEntryType> {
+        self.get_path(path.as_ref()).map(|node| node.data.entry_type)
+    }
+
+    /// Gets every candidate for the file at the local path, in priority order. Directories have no candidates
+    pub fn candidates<P: AsRef<Path>>(&self, path: P) -> &[Candidate] {
+        match self.get_raw_path(path.as_ref()) {
+            Some(node) => &node.data.candidates,
+            None => &[]
+        }
+    }
+
+    /// Gets the index of the candidate that is currently served for the file at the local path
+    pub fn active_candidate<P: AsRef<Path>>(&self, path: P) -> Option<usize> {
+        self.get_raw_path(path.as_ref())
+            .filter(|node| !node.data.candidates.is_empty())
+            .map(|node| node.data.active)
+    }
+
+    /// Selects which candidate is served for the file at the local path. Returns `false` if there is no such file or candidate
+    pub fn set_active_candidate<P: AsRef<Path>>(&mut self, path: P, index: usize) -> bool {
+        match self.get_path_mut(path.as_ref()) {
+            Some(node) if index < node.data.candidates.len() => {
+                node.data.activate(index);
+                true
+            },
+            _ => false
+        }
+    }
+
+    /// Replaces every candidate for the file at the local path and selects the active one.
+    /// Returns `false` if there is no such file, or if `active` is not a valid index into `candidates`
+    pub fn set_candidates<P: AsRef<Path>>(&mut self, path: P, candidates: Vec<Candidate>, active: usize) -> bool {
+        let path = path.as_ref();
+        if path.as_os_str().is_empty() || active >= candidates.len() {
+            return false;
+        }
+        match self.get_path_mut(path) {
+            Some(node) if node.data.entry_type == FileEntryType::File => {
+                node.data.candidates = candidates;
+                node.data.activate(active);
+                true
+            },
+            _ => false
+        }
+    }
+
+    fn walk_raw<F: FnMut(&RawNode<RawTreeNode>)>(&self, mut f: F) {
+        fn internal<F: FnMut(&RawNode<RawTreeNode>)>(node: &RawNode<RawTreeNode>, f: &mut F, depth: usize) {
             if depth != 0 {
-                f(&node.data.raw, node.data.entry_type);
+                f(node);
             }
             for (_, child) in node.children() {
                 internal(child, f, depth + 1);
@@ -309,10 +826,39 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
         internal(&self.root, &mut f, 0);
     }
 
+    /// Recursively walk through the file tree. Whiteouts and entries hidden by an opaque directory are skipped, see `Tree::walk_whiteouts`
+    pub fn walk_paths<F: FnMut(&Node, FileEntryType)>(&self, mut f: F) {
+        fn internal<'a, F: FnMut(&Node, FileEntryType)>(node: &'a RawNode<RawTreeNode>, f: &mut F, markers: &mut Vec<&'a [PathBuf]>) {
+            let marked = !node.data.markers.is_empty();
+            if marked {
+                markers.push(node.data.markers.as_slice());
+            }
+            for (_, child) in node.children() {
+                if !child.data.whiteout && child.data.is_visible(markers) {
+                    f(&child.data.raw, child.data.entry_type);
+                    internal(child, f, markers);
+                }
+            }
+            if marked {
+                markers.pop();
+            }
+        }
+        internal(&self.root, &mut f, &mut Vec::new());
+    }
+
+    /// Recursively walk through every whiteout in the file tree
+    pub fn walk_whiteouts<F: FnMut(&Node)>(&self, mut f: F) {
+        self.walk_raw(|node| {
+            if node.data.whiteout {
+                f(&node.data.raw);
+            }
+        });
+    }
+
     /// Recursively walk through the file tree and declare which entries to keep.
     pub fn filter_walk_paths<C, F: FnMut(&Node, FileEntryType) -> Option<C>>(&mut self, mut f: F) -> Vec<(PathBuf, PathBuf, C)> {
         fn internal<C, F: FnMut(&Node, FileEntryType) -> Option<C>>(node: &mut RawNode<RawTreeNode>, f: &mut F, rejected: &mut Vec<(PathBuf, C)>, depth: usize) {
-            if depth != 0 {
+            if depth != 0 && !node.data.whiteout {
                 if let Some(complaint) = f(&node.data.raw, node.data.entry_type) {
                     rejected.push((node.data.raw.local_path.clone(), complaint));
                     return;
@@ -323,6 +869,7 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
             }
         }
         let mut rejected: Vec<(PathBuf, C)> = Vec::new();
+        self.generation += 1;
         internal(&mut self.root, &mut f, &mut rejected, 0);
         rejected.into_iter().filter_map(|(local_path, reason)| {
             if let Some((root, local)) = self.remove_path(&local_path) {
@@ -333,12 +880,14 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
         }).collect()
     }
 
-    /// Recursively go through the file tree and remove all paths that don't actually exist according to the file loader
+    /// Recursively go through the file tree and remove all paths that don't actually exist according to the file loader.
+    /// Aliases are kept, since they are served from their targets rather than from the loader
     pub fn purify(&mut self) {
         let mut to_remove = Vec::new();
-        self.walk_paths(|node, _| {
-            if !self.loader.path_exists(&node.root_path, &node.local_path) {
-                to_remove.push(node.local_path.clone());
+        self.walk_raw(|node| {
+            let node = &node.data;
+            if !node.whiteout && node.alias.is_none() && !self.loader.path_exists(&node.raw.root_path, node.raw.get_source_local()) {
+                to_remove.push(node.raw.local_path.clone());
             }
         });
         for local_path in to_remove.into_iter() {
@@ -348,26 +897,22 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
 
     /// Get the root path for a specified local path
     pub fn get_root_for_path<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
-        if let Some(node) = self.get_path(path.as_ref()) {
-            Some(node.data.raw.root_path.clone())
-        } else {
-            None
-        }
+        self.get_raw_path(path.as_ref()).map(|node| node.data.raw.root_path.clone())
     }
 
-    /// Get the full path for a specified local path
+    /// Get the full path for a specified local path, following aliases
     pub fn get_full_path<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
-        if let Some(node) = self.get_path(path.as_ref()) {
-            self.loader.get_actual_path(&node.data.raw.root_path, &node.data.raw.local_path)
+        if let Lookup::Node(node) = self.lookup(path.as_ref()) {
+            self.loader.get_actual_path(&node.data.raw.root_path, node.data.raw.get_source_local())
         } else {
             None
         }
     }
 
-    /// Get the filesize for a specified local path
+    /// Get the filesize for a specified local path, following aliases
     pub fn query_filesize<P: AsRef<Path>>(&self, path: P) -> Option<usize> {
-        if let Some(node) = self.get_path(path.as_ref()) {
-            self.loader.get_file_size(&node.data.raw.root_path, &node.data.raw.local_path)
+        if let Lookup::Node(node) = self.lookup(path.as_ref()) {
+            self.loader.get_file_size(&node.data.raw.root_path, node.data.raw.get_source_local())
         } else {
             None
         }
@@ -376,28 +921,25 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
     /// Get the filesize for a specified local path (where the loader is only provided the local path)
     /// NOTE: Intended to be used with virtual loaders
     pub fn query_filesize_local<P: AsRef<Path>>(&self, path: P) -> Option<usize> {
-        self.loader.get_file_size(&Path::new(""), path.as_ref())
+        self.loader.get_file_size(Path::new(""), path.as_ref())
     }
 
-    /// Gets the path type for the provided local path
+    /// Gets the path type for the provided local path, following aliases
     pub fn get_path_type<P: AsRef<Path>>(&self, path: P) -> Result<FileEntryType, L::ErrorType> {
-        let path = path.as_ref();
-        if let Some(node) = self.get_path(path) {
-            if node.data.raw.root_path == Path::new("") {
-                return Ok(FileEntryType::Directory);
-            }
-            self.loader.get_path_type(&node.data.raw.root_path, &node.data.raw.local_path)
-        } else {
-            self.loader.get_path_type(Path::new(""), path)
+        match self.lookup(path.as_ref()) {
+            Lookup::Node(node) if node.data.raw.root_path == Path::new("") => Ok(FileEntryType::Directory),
+            Lookup::Node(node) => self.loader.get_path_type(&node.data.raw.root_path, node.data.raw.get_source_local()),
+            // The loader can't find a dangling alias's target either, so it reports the same error that it does for any unknown path
+            Lookup::Loader(path) | Lookup::Dangling(path) => self.loader.get_path_type(Path::new(""), path)
         }
     }
 
     /// Gets the children for the provided path in terms of the tree
-    pub fn get_children<'a, P: AsRef<Path>>(&'a self, path: P) -> HashSet<&'a Path> {
+    pub fn get_children<P: AsRef<Path>>(&self, path: P) -> HashSet<&Path> {
         let mut paths = HashSet::new();
 
-        if let Some(node) = self.get_path(path.as_ref()) {
-            for path in node.children.values() {
+        if let Some((node, markers)) = self.get_visible_path(path.as_ref()) {
+            for path in node.children.values().filter(|child| !child.data.whiteout && child.data.is_visible(&markers)) {
                 paths.insert(path.data.raw.get_local());
             }
         }
@@ -405,11 +947,12 @@ impl<L: FileLoader> Tree<L> where <L as FileLoader>::ErrorType: Debug {
         paths
     }
 
-    pub fn loader<'a>(&'a self) -> &'a L {
+    pub fn loader(&self) -> &L {
         &self.loader
     }
 
-    pub fn loader_mut<'a>(&'a mut self) -> &'a mut L {
+    pub fn loader_mut(&mut self) -> &mut L {
+        self.generation += 1;
         &mut self.loader
     }
 }
\ No newline at end of file
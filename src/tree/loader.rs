@@ -1,6 +1,8 @@
-use std::path::Path;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 
-use crate::loader::FileLoader;
+use crate::loader::{FileLoader, LoadIntoError, ReadSeek};
 use crate::FileEntryType;
 
 pub struct StandardLoader;
@@ -38,14 +40,65 @@ impl FileLoader for StandardLoader {
     }
 
     fn load_path(&self, root_path: &Path, local_path: &Path) -> Result<Vec<u8>, Self::ErrorType> {
-        let full_path = root_path.join(local_path);
-        if !full_path.exists() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                format!("Filepath '{}' does not exist!", full_path.display())
-            ));
+        std::fs::read(existing_path(root_path, local_path)?)
+    }
+
+    fn open(&self, root_path: &Path, local_path: &Path) -> Result<Box<dyn ReadSeek + '_>, Self::ErrorType> {
+        Ok(Box::new(File::open(existing_path(root_path, local_path)?)?))
+    }
+
+    fn read_at(&self, root_path: &Path, local_path: &Path, offset: u64, buf: &mut [u8]) -> Result<usize, Self::ErrorType> {
+        let file = File::open(existing_path(root_path, local_path)?)?;
+        let mut count = 0;
+        while count < buf.len() {
+            match read_at_offset(&file, &mut buf[count..], offset + count as u64) {
+                Ok(0) => break,
+                Ok(read) => count += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(count)
+    }
+
+    fn load_into(&self, root_path: &Path, local_path: &Path, buf: &mut [u8]) -> Result<usize, LoadIntoError<Self::ErrorType>> {
+        let mut file = File::open(existing_path(root_path, local_path).map_err(LoadIntoError::Loader)?).map_err(LoadIntoError::Loader)?;
+        let size = file.metadata().map_err(LoadIntoError::Loader)?.len() as usize;
+        if size > buf.len() {
+            return Err(LoadIntoError::BufferTooSmall { size, capacity: buf.len() });
         }
+        file.read_exact(&mut buf[..size]).map_err(LoadIntoError::Loader)?;
+        Ok(size)
+    }
+}
 
-        std::fs::read(full_path)
+fn existing_path(root_path: &Path, local_path: &Path) -> io::Result<PathBuf> {
+    let full_path = root_path.join(local_path);
+    if !full_path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Filepath '{}' does not exist!", full_path.display())
+        ));
     }
+
+    Ok(full_path)
+}
+
+#[cfg(unix)]
+fn read_at_offset(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::unix::fs::FileExt;
+    file.read_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at_offset(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::windows::fs::FileExt;
+    file.seek_read(buf, offset)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn read_at_offset(mut file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::io::{Read, Seek, SeekFrom};
+    file.seek(SeekFrom::Start(offset))?;
+    file.read(buf)
 }
\ No newline at end of file
@@ -7,7 +7,8 @@ use super::{TreeError, TreeNode};
 pub struct Node {
     pub(crate) name: String,
     pub(crate) local_path: PathBuf,
-    pub(crate) root_path: PathBuf
+    pub(crate) root_path: PathBuf,
+    pub(crate) mount_path: PathBuf
 }
 
 impl TreeNode for Node {
@@ -39,7 +40,8 @@ impl Node {
         Self {
             name: String::new(),
             root_path: PathBuf::new(),
-            local_path: PathBuf::new()
+            local_path: PathBuf::new(),
+            mount_path: PathBuf::new()
         }
     }
 
@@ -54,10 +56,25 @@ impl Node {
         Ok(Self {
             name,
             local_path,
-            root_path
+            root_path,
+            mount_path: PathBuf::new()
         })
     }
 
+    /// Returns a `Node` for a root which is mounted at `mount_path` in the file tree, so that `local_path` (which must be inside of the mount path)
+    /// is found at `local_path` without the mount path inside of the root
+    pub fn new_mounted<A: AsRef<Path>, B: AsRef<Path>, C: AsRef<Path>>(root_path: A, mount_path: B, local_path: C) -> Result<Self, TreeError> {
+        let mount_path = mount_path.as_ref();
+        let local_path = local_path.as_ref();
+        if !local_path.starts_with(mount_path) {
+            return Err(TreeError::OutsideMount(local_path.to_path_buf(), mount_path.to_path_buf()));
+        }
+
+        let mut node = Self::new(root_path, local_path)?;
+        node.mount_path = mount_path.to_path_buf();
+        Ok(node)
+    }
+
     /// Changes the root this `Node` is based in. Changing the root has no impact over its location in the file tree.
     pub fn change_root<A: AsRef<Path>>(&mut self, new_root: A) -> Result<(), TreeError> {
         let new_root = new_root.as_ref();
@@ -68,7 +85,7 @@ impl Node {
 
     /// Returns the full path of the Node
     pub fn full_path(&self) -> PathBuf {
-        self.root_path.join(&self.local_path)
+        self.root_path.join(self.get_source_local())
     }
 
     pub fn get_root(&self) -> &Path {
@@ -78,4 +95,15 @@ impl Node {
     pub fn get_local(&self) -> &Path {
         self.local_path.as_path()
     }
+
+    /// Gets the local path that the root is mounted at in the file tree, which is empty unless the node was created with `Node::new_mounted`
+    pub fn get_mount(&self) -> &Path {
+        self.mount_path.as_path()
+    }
+
+    /// Gets the local path of the node inside of its root, which is the local path without the mount path.
+    /// This is the local path that the loader is given
+    pub fn get_source_local(&self) -> &Path {
+        self.local_path.strip_prefix(&self.mount_path).unwrap_or(&self.local_path)
+    }
 }
\ No newline at end of file